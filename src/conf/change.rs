@@ -0,0 +1,223 @@
+use std::fmt;
+
+use super::{
+    Conf, EffisRateLimitConf, EmailConf, OprishRoute, RateLimitConf, RateLimitPreset,
+    RateLimitTier, RegistrationConf,
+};
+
+/// A single difference between two [`Conf`]s.
+///
+/// These are produced by [`Conf::diff`] so that running services can react to a reloaded config,
+/// for example by swapping in new rate limits.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfChange {
+    InstanceName {
+        old: String,
+        new: String,
+    },
+    Description {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    EmailAddress {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    RateLimitPreset {
+        old: RateLimitPreset,
+        new: RateLimitPreset,
+    },
+    OprishUrl {
+        old: String,
+        new: String,
+    },
+    MessageLimit {
+        old: usize,
+        new: usize,
+    },
+    BioLimit {
+        old: usize,
+        new: usize,
+    },
+    OprishRateLimit {
+        /// The route whose rate limit changed.
+        route: OprishRoute,
+        old: RateLimitConf,
+        new: RateLimitConf,
+    },
+    OprishRateLimitTiers {
+        old: Vec<RateLimitTier>,
+        new: Vec<RateLimitTier>,
+    },
+    Registration {
+        old: RegistrationConf,
+        new: RegistrationConf,
+    },
+    PandemoniumUrl {
+        old: String,
+        new: String,
+    },
+    PandemoniumRateLimit {
+        old: RateLimitConf,
+        new: RateLimitConf,
+    },
+    EffisUrl {
+        old: String,
+        new: String,
+    },
+    FileSize {
+        old: u64,
+        new: u64,
+    },
+    AttachmentFileSize {
+        old: u64,
+        new: u64,
+    },
+    EffisAssetsRateLimit {
+        old: EffisRateLimitConf,
+        new: EffisRateLimitConf,
+    },
+    EffisAttachmentsRateLimit {
+        old: EffisRateLimitConf,
+        new: EffisRateLimitConf,
+    },
+    EffisFetchFileRateLimit {
+        old: RateLimitConf,
+        new: RateLimitConf,
+    },
+    Email {
+        old: Option<EmailConf>,
+        new: Option<EmailConf>,
+    },
+    /// A secret changed. The old and new values are deliberately not included.
+    Secret {
+        /// The dotted path of the secret.
+        path: &'static str,
+    },
+}
+
+impl ConfChange {
+    /// Get the dotted path of the changed config field.
+    pub fn path(&self) -> String {
+        match self {
+            Self::InstanceName { .. } => "instance_name".to_string(),
+            Self::Description { .. } => "description".to_string(),
+            Self::EmailAddress { .. } => "email_address".to_string(),
+            Self::RateLimitPreset { .. } => "rate_limit_preset".to_string(),
+            Self::OprishUrl { .. } => "oprish.url".to_string(),
+            Self::MessageLimit { .. } => "oprish.message_limit".to_string(),
+            Self::BioLimit { .. } => "oprish.bio_limit".to_string(),
+            Self::OprishRateLimit { route, .. } => format!("oprish.rate_limits.{}", route),
+            Self::OprishRateLimitTiers { .. } => "oprish.rate_limit_tiers".to_string(),
+            Self::Registration { .. } => "oprish.registration".to_string(),
+            Self::PandemoniumUrl { .. } => "pandemonium.url".to_string(),
+            Self::PandemoniumRateLimit { .. } => "pandemonium.rate_limit".to_string(),
+            Self::EffisUrl { .. } => "effis.url".to_string(),
+            Self::FileSize { .. } => "effis.file_size".to_string(),
+            Self::AttachmentFileSize { .. } => "effis.attachment_file_size".to_string(),
+            Self::EffisAssetsRateLimit { .. } => "effis.rate_limits.assets".to_string(),
+            Self::EffisAttachmentsRateLimit { .. } => "effis.rate_limits.attachments".to_string(),
+            Self::EffisFetchFileRateLimit { .. } => "effis.rate_limits.fetch_file".to_string(),
+            Self::Email { .. } => "email".to_string(),
+            Self::Secret { path } => path.to_string(),
+        }
+    }
+
+    /// Whether the change affects the instance's [`InstanceInfo`](crate::InstanceInfo),
+    /// meaning that clients should be sent the new one.
+    pub fn affects_instance_info(&self) -> bool {
+        !matches!(
+            self,
+            Self::RateLimitPreset { .. }
+                | Self::OprishRateLimitTiers { .. }
+                | Self::Registration { .. }
+                | Self::Email { .. }
+                | Self::Secret { .. }
+        )
+    }
+}
+
+impl fmt::Display for ConfChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} changed", self.path())
+    }
+}
+
+macro_rules! diff_fields {
+    ($changes:expr, $old:expr, $new:expr, $($variant:ident => $($field:ident).+),+ $(,)?) => {
+        $(
+            if $old.$($field).+ != $new.$($field).+ {
+                $changes.push(ConfChange::$variant {
+                    old: $old.$($field).+.clone(),
+                    new: $new.$($field).+.clone(),
+                });
+            }
+        )+
+    };
+}
+
+impl Conf {
+    /// Get every difference between this config and a `new` one.
+    pub fn diff(&self, new: &Conf) -> Vec<ConfChange> {
+        let mut changes = vec![];
+
+        diff_fields!(
+            changes,
+            self,
+            new,
+            InstanceName => instance_name,
+            Description => description,
+            EmailAddress => email_address,
+            RateLimitPreset => rate_limit_preset,
+            OprishUrl => oprish.url,
+            MessageLimit => oprish.message_limit,
+            BioLimit => oprish.bio_limit,
+        );
+        for route in OprishRoute::ALL {
+            let (old, new) = (
+                self.oprish.rate_limits.get(route),
+                new.oprish.rate_limits.get(route),
+            );
+            if old != new {
+                changes.push(ConfChange::OprishRateLimit {
+                    route,
+                    old: old.clone(),
+                    new: new.clone(),
+                });
+            }
+        }
+        diff_fields!(
+            changes,
+            self,
+            new,
+            OprishRateLimitTiers => oprish.rate_limit_tiers,
+            Registration => oprish.registration,
+            PandemoniumUrl => pandemonium.url,
+            PandemoniumRateLimit => pandemonium.rate_limit,
+            EffisUrl => effis.url,
+            FileSize => effis.file_size,
+            AttachmentFileSize => effis.attachment_file_size,
+            EffisAssetsRateLimit => effis.rate_limits.assets,
+            EffisAttachmentsRateLimit => effis.rate_limits.attachments,
+            EffisFetchFileRateLimit => effis.rate_limits.fetch_file,
+            Email => email,
+        );
+        for (path, changed) in [
+            (
+                "secrets.database_url",
+                self.secrets.database_url != new.secrets.database_url,
+            ),
+            (
+                "secrets.session_secret",
+                self.secrets.session_secret != new.secrets.session_secret,
+            ),
+            ("secrets.smtp", self.secrets.smtp != new.secrets.smtp),
+        ] {
+            if changed {
+                changes.push(ConfChange::Secret { path });
+            }
+        }
+
+        changes
+    }
+}
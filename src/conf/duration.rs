@@ -0,0 +1,183 @@
+//! (De)serialization of configuration durations.
+//!
+//! Durations are deserialized from either an amount of seconds or a human readable string like
+//! `"5m"`, `"1h30m"` or `"250ms"`, with millisecond precision. They are serialized as an amount of
+//! seconds, which is only fractional if the duration isn't a whole amount of seconds.
+use std::{fmt, time::Duration};
+
+use serde::{
+    de::{self, Visitor},
+    Deserializer, Serializer,
+};
+
+pub(crate) fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if duration.subsec_nanos() == 0 {
+        serializer.serialize_u64(duration.as_secs())
+    } else {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+}
+
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(DurationVisitor)
+}
+
+struct DurationVisitor;
+
+impl Visitor<'_> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an amount of seconds or a duration string like \"1h30m\"")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Duration::from_secs(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        u64::try_from(value)
+            .map(Duration::from_secs)
+            .map_err(|_| E::custom("duration can't be negative"))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        millis(value, 1000).ok_or_else(|| E::custom("duration must be a positive number"))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        parse(value).ok_or_else(|| E::custom(format!("invalid duration \"{}\"", value)))
+    }
+}
+
+/// Parse a human readable duration like `"1h30m"`.
+///
+/// Every component is a (possibly fractional) number followed by one of the `ms`, `s`, `m`, `h`
+/// or `d` units. A bare number is an amount of seconds.
+fn parse(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return millis(seconds, 1000);
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter(|&end| end > 0)?;
+        let (number, tail) = rest.split_at(number_end);
+        let unit_end = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_end);
+        let unit_millis = match unit {
+            "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            _ => return None,
+        };
+        total = total.checked_add(millis(number.parse().ok()?, unit_millis)?)?;
+        rest = tail.trim_start();
+    }
+
+    (!value.is_empty()).then_some(total)
+}
+
+/// Convert an amount of some unit into a [`Duration`], rounded to the nearest millisecond.
+fn millis(amount: f64, unit_millis: u64) -> Option<Duration> {
+    let millis = (amount * unit_millis as f64).round();
+    (millis.is_finite() && millis >= 0.0 && millis <= u64::MAX as f64)
+        .then(|| Duration::from_millis(millis as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] Duration);
+
+    #[test]
+    fn parsing() {
+        let cases = [
+            ("1h30m", Some(Duration::from_secs(90 * 60))),
+            ("250ms", Some(Duration::from_millis(250))),
+            ("1.5h", Some(Duration::from_secs(90 * 60))),
+            ("1d 2h", Some(Duration::from_secs(26 * 60 * 60))),
+            (" 42 ", Some(Duration::from_secs(42))),
+            ("0.25", Some(Duration::from_millis(250))),
+            ("0.0004s", Some(Duration::ZERO)),
+            ("0.0006s", Some(Duration::from_millis(1))),
+            ("1h30", None),
+            ("30x", None),
+            ("h", None),
+            ("", None),
+            ("-1", None),
+            ("-1s", None),
+            ("inf", None),
+            ("NaN", None),
+            ("1e400", None),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(parse(value), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn deserialization() {
+        let cases = [
+            ("5", Some(Duration::from_secs(5))),
+            ("1.5", Some(Duration::from_millis(1500))),
+            ("\"1h30m\"", Some(Duration::from_secs(90 * 60))),
+            ("-1", None),
+            ("-0.5", None),
+            ("\"soon\"", None),
+        ];
+
+        for (value, expected) in cases {
+            let duration = serde_json::from_str::<Wrapper>(value).ok().map(|w| w.0);
+            assert_eq!(duration, expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn serialization() {
+        let cases = [
+            (Duration::from_secs(5), "5"),
+            (Duration::ZERO, "0"),
+            (Duration::from_millis(1500), "1.5"),
+            (Duration::from_millis(250), "0.25"),
+            (Duration::from_millis(1), "0.001"),
+        ];
+
+        for (duration, expected) in cases {
+            assert_eq!(serde_json::to_string(&Wrapper(duration)).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for millis in [1, 3, 10, 99, 250, 333, 999, 1_001, 86_399_999] {
+            let duration = Wrapper(Duration::from_millis(millis));
+            let value = serde_json::to_string(&duration).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Wrapper>(&value).unwrap(),
+                duration,
+                "{}",
+                value
+            );
+        }
+    }
+}
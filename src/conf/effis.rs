@@ -1,3 +1,5 @@
+use std::{fmt, time::Duration};
+
 use serde::{Deserialize, Deserializer, Serialize};
 use ubyte::ByteUnit;
 
@@ -11,11 +13,15 @@ pub struct EffisConf {
     pub file_size: u64,
     #[serde(deserialize_with = "deserialize_file_size")]
     pub attachment_file_size: u64,
+    #[serde(default)]
     pub rate_limits: EffisRateLimits,
 }
 
 /// Rate limits that apply to Effis (The CDN).
 ///
+/// Missing rate limits fall back to the configured [`RateLimitPreset`](super::RateLimitPreset)
+/// when loaded with `Conf::new`, and to the public preset when deserialized directly.
+///
 /// -----
 ///
 /// ### Example
@@ -39,6 +45,7 @@ pub struct EffisConf {
 /// }
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EffisRateLimits {
     /// Rate limits for the asset buckets.
     pub assets: EffisRateLimitConf,
@@ -63,8 +70,12 @@ pub struct EffisRateLimits {
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EffisRateLimitConf {
-    /// The amount of seconds after which the rate limit resets.
-    pub reset_after: u32,
+    /// The duration after which the rate limit resets.
+    ///
+    /// This is an amount of seconds, which can be fractional. Configs can also use human readable
+    /// durations like `"1h30m"` or `"250ms"`.
+    #[serde(with = "super::duration")]
+    pub reset_after: Duration,
     /// The amount of requests that can be made within the `reset_after` interval.
     pub limit: u32,
     /// The maximum amount of bytes that can be sent within the `reset_after` interval.
@@ -72,6 +83,37 @@ pub struct EffisRateLimitConf {
     pub file_size_limit: u64,
 }
 
+/// The Effis buckets that have their own rate limit.
+///
+/// This is a string in `snake_case`, matching the [`EffisRateLimits`] field names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffisRoute {
+    Assets,
+    Attachments,
+    FetchFile,
+}
+
+impl EffisRoute {
+    /// All the Effis buckets.
+    pub const ALL: [Self; 3] = [Self::Assets, Self::Attachments, Self::FetchFile];
+
+    /// Get the name of the bucket's [`EffisRateLimits`] field.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Assets => "assets",
+            Self::Attachments => "attachments",
+            Self::FetchFile => "fetch_file",
+        }
+    }
+}
+
+impl fmt::Display for EffisRoute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 pub(crate) fn deserialize_file_size<'de, D>(deserializer: D) -> Result<u64, D::Error>
 where
     D: Deserializer<'de>,
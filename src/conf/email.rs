@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+/// Email configuration.
+///
+/// The SMTP relay's credentials are configured in the
+/// [`SecretsConf`](super::SecretsConf)'s `smtp` section.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```toml
+/// [email]
+/// relay = "smtp.example.com"
+/// tls = "starttls"
+/// name = "WooChat"
+/// address = "noreply@example.com"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailConf {
+    /// The host of the SMTP relay emails are sent through.
+    pub relay: String,
+    /// The port of the SMTP relay, defaults to the standard port of the [`SmtpTls`] mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// How the connection to the SMTP relay is secured.
+    #[serde(default)]
+    pub tls: SmtpTls,
+    /// The name emails are sent as, defaults to the instance's name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The address emails are sent from.
+    pub address: String,
+}
+
+impl EmailConf {
+    /// Get the port of the SMTP relay.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or_else(|| self.tls.default_port())
+    }
+}
+
+/// How the connection to an SMTP relay is secured.
+///
+/// This is a string.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// No encryption at all, this should only be used for local relays and testing.
+    None,
+    /// Upgrade a plain text connection using `STARTTLS`.
+    #[default]
+    StartTls,
+    /// Connect using implicit TLS.
+    Tls,
+}
+
+impl SmtpTls {
+    /// Get the standard SMTP port of the TLS mode.
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::None => 25,
+            Self::StartTls => 587,
+            Self::Tls => 465,
+        }
+    }
+}
@@ -0,0 +1,417 @@
+use std::{env, fs, path::Path};
+
+use anyhow::Context;
+use toml::{Table, Value};
+
+use super::{Conf, RateLimitPreset};
+
+/// The prefix of all environment variables that override configuration values.
+const ENV_PREFIX: &str = "ELUDRIS_";
+/// The environment variable that holds the path of the configuration file.
+const CONF_PATH_ENV: &str = "ELUDRIS_CONF";
+
+impl Conf {
+    /// Create a new [`Conf`] from the TOML file at `path`.
+    ///
+    /// `ELUDRIS_*` environment variables are applied on top of the file's values, with nested keys
+    /// separated by double underscores. For example `ELUDRIS_OPRISH__MESSAGE_LIMIT=4096` overrides
+    /// `message_limit` in the `oprish` table.
+    ///
+    /// Override values are parsed as TOML values, falling back to plain strings if they aren't
+    /// valid TOML or don't fit their field, like an `ELUDRIS_INSTANCE_NAME` of `1984`. Any rate limit
+    /// or rate limit field that is still missing afterwards is taken from the configured
+    /// [`RateLimitPreset`]. The resulting config is validated using [`Conf::validate`].
+    pub fn new<T: AsRef<Path>>(path: T) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Could not read config file {}", path.display()))?;
+        Self::from_toml(&data, env::vars())
+    }
+
+    /// Create a new [`Conf`] by determining its path based on the `ELUDRIS_CONF` environment
+    /// variable or falling back to `Eludris.toml` if it is not set.
+    pub fn new_from_env() -> anyhow::Result<Self> {
+        Self::new(conf_path_from_env())
+    }
+
+    /// Create a new [`Conf`] from a TOML string, applying the `ELUDRIS_*` overrides found in
+    /// `vars` and validating the result.
+    pub fn from_toml<I>(data: &str, vars: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut table: Table = toml::from_str(data).context("Could not parse config file")?;
+        let overrides = apply_env_overrides(&mut table, vars)?;
+        let preset: RateLimitPreset = match table.get("rate_limit_preset") {
+            Some(preset) => preset
+                .clone()
+                .try_into()
+                .context("Invalid rate_limit_preset")?,
+            None => RateLimitPreset::default(),
+        };
+        let assets_budget = contains_key(
+            &table,
+            &["effis", "rate_limits", "assets", "file_size_limit"],
+        );
+        let attachments_budget = contains_key(
+            &table,
+            &["effis", "rate_limits", "attachments", "file_size_limit"],
+        );
+        let mut merged = preset_table(preset)?;
+        merge_tables(&mut merged, table);
+        let mut conf = deserialize(merged, overrides).context("Could not deserialize config")?;
+        // The preset's byte budgets don't know about the instance's file sizes, they're raised so
+        // that a single file always fits.
+        let effis = &mut conf.effis;
+        if !assets_budget {
+            effis.rate_limits.assets.file_size_limit = effis
+                .rate_limits
+                .assets
+                .file_size_limit
+                .max(effis.file_size);
+        }
+        if !attachments_budget {
+            effis.rate_limits.attachments.file_size_limit = effis
+                .rate_limits
+                .attachments
+                .file_size_limit
+                .max(effis.attachment_file_size);
+        }
+        conf.validate()?;
+        Ok(conf)
+    }
+}
+
+pub(super) fn conf_path_from_env() -> String {
+    env::var(CONF_PATH_ENV).unwrap_or_else(|_| "Eludris.toml".to_string())
+}
+
+/// An override whose value was parsed as something other than a string.
+struct TypedOverride {
+    path: Vec<String>,
+    raw: String,
+}
+
+/// Apply the `ELUDRIS_*` overrides in `vars`, returning the ones that were parsed as something
+/// other than a string.
+fn apply_env_overrides<I>(table: &mut Table, vars: I) -> anyhow::Result<Vec<TypedOverride>>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut overrides = Vec::new();
+    for (key, value) in vars {
+        if key == CONF_PATH_ENV {
+            continue;
+        }
+        let Some(path) = key.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let path: Vec<String> = path.split("__").map(str::to_lowercase).collect();
+        if path.iter().any(String::is_empty) {
+            anyhow::bail!("Invalid config override variable {}", key);
+        }
+        let parsed = parse_value(&value);
+        let typed = !parsed.is_str();
+        set_value(table, &path, parsed)
+            .with_context(|| format!("Could not apply config override {}", key))?;
+        if typed {
+            overrides.push(TypedOverride { path, raw: value });
+        }
+    }
+    Ok(overrides)
+}
+
+/// Deserialize a config, retrying overrides whose parsed value doesn't fit their field with the
+/// raw string they were set to.
+fn deserialize(mut table: Table, mut overrides: Vec<TypedOverride>) -> anyhow::Result<Conf> {
+    loop {
+        let err = match serde_path_to_error::deserialize(Value::Table(table.clone())) {
+            Ok(conf) => return Ok(conf),
+            Err(err) => err,
+        };
+        let path = err.path().to_string();
+        match overrides
+            .iter()
+            .position(|typed| typed.path.join(".") == path)
+        {
+            Some(index) => {
+                let typed = overrides.swap_remove(index);
+                set_value(&mut table, &typed.path, Value::String(typed.raw))?;
+            }
+            None => return Err(err.into_inner().into()),
+        }
+    }
+}
+
+fn set_value(table: &mut Table, path: &[String], value: Value) -> anyhow::Result<()> {
+    match path {
+        [] => unreachable!(),
+        [key] => {
+            table.insert(key.clone(), value);
+        }
+        [key, rest @ ..] => {
+            let entry = table
+                .entry(key.clone())
+                .or_insert_with(|| Value::Table(Table::new()));
+            match entry {
+                Value::Table(table) => set_value(table, rest, value)?,
+                _ => anyhow::bail!("{} is not a table", key),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn preset_table(preset: RateLimitPreset) -> anyhow::Result<Table> {
+    let mut oprish = Table::new();
+    oprish.insert(
+        "rate_limits".to_string(),
+        Value::try_from(preset.oprish_rate_limits())?,
+    );
+    let mut pandemonium = Table::new();
+    pandemonium.insert(
+        "rate_limit".to_string(),
+        Value::try_from(preset.pandemonium_rate_limit())?,
+    );
+    let mut effis = Table::new();
+    effis.insert(
+        "rate_limits".to_string(),
+        Value::try_from(preset.effis_rate_limits())?,
+    );
+
+    let mut table = Table::new();
+    table.insert("oprish".to_string(), Value::Table(oprish));
+    table.insert("pandemonium".to_string(), Value::Table(pandemonium));
+    table.insert("effis".to_string(), Value::Table(effis));
+    Ok(table)
+}
+
+/// Whether the value at `path` is set in `table`.
+fn contains_key(table: &Table, path: &[&str]) -> bool {
+    match path {
+        [] => true,
+        [key, rest @ ..] => match table.get(*key) {
+            Some(Value::Table(table)) => contains_key(table, rest),
+            Some(_) => rest.is_empty(),
+            None => false,
+        },
+    }
+}
+
+/// Recursively merge `overrides` into `base`, with `overrides` taking precedence.
+fn merge_tables(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overrides)) => merge_tables(base, overrides),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn parse_value(value: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{conf::RateLimitConf, fixtures::CONF};
+
+    fn load(data: &str, vars: &[(&str, &str)]) -> anyhow::Result<Conf> {
+        Conf::from_toml(
+            data,
+            vars.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        )
+    }
+
+    #[test]
+    fn overrides() {
+        let conf = load(
+            CONF,
+            &[
+                ("ELUDRIS_OPRISH__MESSAGE_LIMIT", "4096"),
+                ("ELUDRIS_PANDEMONIUM__RATE_LIMIT__LIMIT", "7"),
+                ("ELUDRIS_DESCRIPTION", "The poggest place to chat"),
+                ("ELUDRIS_EFFIS__FILE_SIZE", "\"10MB\""),
+                ("ELUDRIS_CONF", "Other.toml"),
+                ("DESCRIPTION", "Not an override"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(conf.oprish.message_limit, 4096);
+        assert_eq!(conf.pandemonium.rate_limit.limit, 7);
+        assert_eq!(
+            conf.description.as_deref(),
+            Some("The poggest place to chat")
+        );
+        assert_eq!(conf.effis.file_size, 10_000_000);
+    }
+
+    #[test]
+    fn overrides_fall_back_to_strings() {
+        let conf = load(
+            CONF,
+            &[
+                ("ELUDRIS_INSTANCE_NAME", "1984"),
+                ("ELUDRIS_DESCRIPTION", "true"),
+                (
+                    "ELUDRIS_SECRETS__SESSION_SECRET",
+                    "3.14159265358979323846264338327950",
+                ),
+                ("ELUDRIS_OPRISH__BIO_LIMIT", "300"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(conf.instance_name, "1984");
+        assert_eq!(conf.description.as_deref(), Some("true"));
+        assert_eq!(
+            conf.secrets
+                .session_secret
+                .as_ref()
+                .map(|secret| secret.expose()),
+            Some("3.14159265358979323846264338327950")
+        );
+        assert_eq!(conf.oprish.bio_limit, 300);
+    }
+
+    #[test]
+    fn invalid_overrides() {
+        let err = load(CONF, &[("ELUDRIS_OPRISH__MESSAGE_LIMIT", "lots")]).unwrap_err();
+        assert_eq!(err.to_string(), "Could not deserialize config");
+
+        let err = load(CONF, &[("ELUDRIS_OPRISH____URL", "x")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid config override variable ELUDRIS_OPRISH____URL"
+        );
+
+        let err = load(CONF, &[("ELUDRIS_INSTANCE_NAME__FOO", "x")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Could not apply config override ELUDRIS_INSTANCE_NAME__FOO"
+        );
+    }
+
+    #[test]
+    fn preset_defaults() {
+        let conf = load(CONF, &[]).unwrap();
+        let preset = RateLimitPreset::Public;
+
+        assert_eq!(conf.rate_limit_preset, preset);
+        assert_eq!(conf.oprish.rate_limits, preset.oprish_rate_limits());
+        assert_eq!(conf.pandemonium.rate_limit, preset.pandemonium_rate_limit());
+        assert_eq!(conf.effis.rate_limits, preset.effis_rate_limits());
+    }
+
+    #[test]
+    fn preset_merging() {
+        let conf = load(
+            &format!(
+                "rate_limit_preset = \"strict\"\n{}\n{}",
+                CONF,
+                r#"
+[oprish.rate_limits.create_message]
+limit = 1
+
+[effis.rate_limits.fetch_file]
+reset_after = "1m"
+limit = 100
+"#
+            ),
+            &[("ELUDRIS_PANDEMONIUM__RATE_LIMIT__RESET_AFTER", "\"250ms\"")],
+        )
+        .unwrap();
+        let preset = RateLimitPreset::Strict;
+
+        assert_eq!(conf.rate_limit_preset, preset);
+        // Missing fields of partially configured rate limits come from the preset.
+        assert_eq!(
+            conf.oprish.rate_limits.create_message,
+            RateLimitConf {
+                limit: 1,
+                ..preset.oprish_rate_limits().create_message
+            }
+        );
+        assert_eq!(
+            conf.oprish.rate_limits.get_user,
+            preset.oprish_rate_limits().get_user
+        );
+        assert_eq!(
+            conf.pandemonium.rate_limit,
+            RateLimitConf {
+                reset_after: Duration::from_millis(250),
+                ..preset.pandemonium_rate_limit()
+            }
+        );
+        assert_eq!(
+            conf.effis.rate_limits.fetch_file,
+            RateLimitConf {
+                reset_after: Duration::from_secs(60),
+                limit: 100,
+            }
+        );
+        assert_eq!(
+            conf.effis.rate_limits.assets,
+            preset.effis_rate_limits().assets
+        );
+    }
+
+    #[test]
+    fn preset_override() {
+        let conf = load(CONF, &[("ELUDRIS_RATE_LIMIT_PRESET", "development")]).unwrap();
+        let preset = RateLimitPreset::Development;
+
+        assert_eq!(conf.rate_limit_preset, preset);
+        assert_eq!(conf.oprish.rate_limits, preset.oprish_rate_limits());
+
+        let err = load(CONF, &[("ELUDRIS_RATE_LIMIT_PRESET", "lenient")]).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid rate_limit_preset");
+    }
+
+    #[test]
+    fn preset_byte_budgets_fit_a_file() {
+        let vars = [
+            ("ELUDRIS_RATE_LIMIT_PRESET", "strict"),
+            ("ELUDRIS_EFFIS__FILE_SIZE", "50MB"),
+            ("ELUDRIS_EFFIS__ATTACHMENT_FILE_SIZE", "600MB"),
+        ];
+        let conf = load(CONF, &vars).unwrap();
+        let preset = RateLimitPreset::Strict.effis_rate_limits();
+
+        assert_eq!(conf.effis.rate_limits.assets.file_size_limit, 50_000_000);
+        assert_eq!(
+            conf.effis.rate_limits.attachments.file_size_limit,
+            600_000_000
+        );
+        assert_eq!(conf.effis.rate_limits.assets.limit, preset.assets.limit);
+
+        // Budgets larger than a file are kept.
+        let conf = load(CONF, &vars[..1]).unwrap();
+        assert_eq!(conf.effis.rate_limits, preset);
+
+        // Explicitly configured budgets aren't touched.
+        let err = load(
+            &format!(
+                "{}\n[effis.rate_limits.assets]\nfile_size_limit = \"30MB\"\n",
+                CONF
+            ),
+            &vars,
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "Invalid config\n  - effis.rate_limits.assets.file_size_limit: Can't be smaller than a \
+             single file (50000000 bytes)"
+        );
+    }
+}
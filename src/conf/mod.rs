@@ -1,12 +1,92 @@
+//! Simple abstraction for a TOML based Eludris configuration file.
+mod change;
+mod duration;
 mod effis;
+mod email;
+#[cfg(feature = "logic")]
+mod loader;
 mod oprish;
 mod pandemonium;
+mod preset;
+mod registration;
+mod secret;
+#[cfg(feature = "logic")]
+mod validation;
+#[cfg(feature = "logic")]
+mod watcher;
+
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
+pub use change::*;
 pub use effis::*;
+pub use email::*;
 pub use oprish::*;
 pub use pandemonium::*;
+pub use preset::*;
+pub use registration::*;
+pub use secret::*;
+#[cfg(feature = "logic")]
+pub use validation::*;
+#[cfg(feature = "logic")]
+pub use watcher::*;
+
+/// Eludris configuration.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```toml
+/// instance_name = "WooChat"
+/// description = "The poggest place to chat"
+///
+/// [oprish]
+/// url = "https://example.com"
+/// message_limit = 2000
+/// bio_limit = 250
+///
+/// [pandemonium]
+/// url = "wss://example.com"
+///
+/// [effis]
+/// url = "https://cdn.example.com"
+/// file_size = "20MB"
+/// attachment_file_size = "25MB"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conf {
+    /// The instance's name.
+    pub instance_name: String,
+    /// The instance's description.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The instance's email address if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_address: Option<String>,
+    /// The preset that rate limits which aren't explicitly configured fall back to.
+    ///
+    /// This is only applied by `Conf::new` and `Conf::from_toml`, see [`RateLimitPreset`].
+    #[serde(default)]
+    pub rate_limit_preset: RateLimitPreset,
+    /// The instance's Oprish (REST API) configuration.
+    pub oprish: OprishConf,
+    /// The instance's Pandemonium (WebSocket API) configuration.
+    pub pandemonium: PandemoniumConf,
+    /// The instance's Effis (CDN) configuration.
+    pub effis: EffisConf,
+    /// The instance's email configuration.
+    ///
+    /// The instance can't send any emails if this is not present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<EmailConf>,
+    /// The instance's secrets.
+    ///
+    /// These are redacted when the config is formatted or serialized.
+    #[serde(default)]
+    pub secrets: SecretsConf,
+}
 
 /// Represents a single rate limit.
 ///
@@ -22,8 +102,12 @@ pub use pandemonium::*;
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RateLimitConf {
-    /// The amount of seconds after which the rate limit resets.
-    pub reset_after: u32,
+    /// The duration after which the rate limit resets.
+    ///
+    /// This is an amount of seconds, which can be fractional. Configs can also use human readable
+    /// durations like `"1h30m"` or `"250ms"`.
+    #[serde(with = "duration")]
+    pub reset_after: Duration,
     /// The amount of requests that can be made within the `reset_after` interval.
     pub limit: u32,
 }
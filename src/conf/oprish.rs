@@ -1,6 +1,9 @@
+use std::{collections::BTreeMap, fmt};
+
 use serde::{Deserialize, Serialize};
 
-use super::RateLimitConf;
+use super::{RateLimitConf, RegistrationConf};
+use crate::User;
 
 /// Oprish configuration.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -8,11 +11,101 @@ pub struct OprishConf {
     pub url: String,
     pub message_limit: usize,
     pub bio_limit: usize,
+    #[serde(default)]
     pub rate_limits: OprishRateLimits,
+    /// Rate limit overrides for specific groups of users.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rate_limit_tiers: Vec<RateLimitTier>,
+    /// Who can create an account on the instance.
+    #[serde(default)]
+    pub registration: RegistrationConf,
+}
+
+impl OprishConf {
+    /// Get the rate limit that applies to a `user` on an Oprish route.
+    ///
+    /// The first [`RateLimitTier`] that matches the user and overrides the route's rate limit
+    /// wins, otherwise the instance-wide rate limit is used. A `user` of `None` is a guest.
+    pub fn rate_limit_for(&self, route: OprishRoute, user: Option<&User>) -> &RateLimitConf {
+        self.rate_limit_tiers
+            .iter()
+            .filter(|tier| tier.matches(user))
+            .find_map(|tier| tier.rate_limits.get(&route))
+            .unwrap_or_else(|| self.rate_limits.get(route))
+    }
+}
+
+/// A set of rate limit overrides that apply to the users matching all of its criteria.
+///
+/// A tier without any criteria matches everyone, guests included.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```toml
+/// [[oprish.rate_limit_tiers]]
+/// name = "verified"
+/// verified = true
+///
+/// [oprish.rate_limit_tiers.rate_limits]
+/// create_message = { reset_after = "5s", limit = 20 }
+///
+/// [[oprish.rate_limit_tiers]]
+/// name = "guests"
+/// guest = true
+///
+/// [oprish.rate_limit_tiers.rate_limits]
+/// guest_get_user = { reset_after = "5s", limit = 5 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitTier {
+    /// The tier's name.
+    pub name: String,
+    /// The permission bits a user needs to all have to be in the tier.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub permissions: u64,
+    /// The badge bits a user needs to all have to be in the tier.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub badges: u64,
+    /// The verification status a user needs to have to be in the tier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    /// Whether the tier only applies to guests or only applies to users.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guest: Option<bool>,
+    /// The rate limits the tier overrides.
+    pub rate_limits: BTreeMap<OprishRoute, RateLimitConf>,
+}
+
+impl RateLimitTier {
+    /// Whether a `user` is in the tier. A `user` of `None` is a guest.
+    pub fn matches(&self, user: Option<&User>) -> bool {
+        if self.guest.is_some_and(|guest| guest != user.is_none()) {
+            return false;
+        }
+        match user {
+            Some(user) => {
+                user.permissions & self.permissions == self.permissions
+                    && user.badges & self.badges == self.badges
+                    && self
+                        .verified
+                        .is_none_or(|verified| user.verified.unwrap_or(false) == verified)
+            }
+            None => self.permissions == 0 && self.badges == 0 && self.verified.is_none(),
+        }
+    }
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
 }
 
 /// Rate limits that apply to Oprish (The REST API).
 ///
+/// Missing rate limits fall back to the configured [`RateLimitPreset`](super::RateLimitPreset)
+/// when loaded with `Conf::new`, and to the public preset when deserialized directly.
+///
 /// -----
 ///
 /// ### Example
@@ -32,11 +125,16 @@ pub struct OprishConf {
 /// }
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct OprishRateLimits {
     /// Rate limits for the [`get_instance_info`] endpoint.
     pub get_instance_info: RateLimitConf,
     /// Rate limits for the [`create_message`] endpoint.
     pub create_message: RateLimitConf,
+    /// Rate limits for the [`edit_message`] endpoint.
+    pub edit_message: RateLimitConf,
+    /// Rate limits for the [`delete_message`] endpoint.
+    pub delete_message: RateLimitConf,
     /// Rate limits for the [`create_user`] endpoint.
     pub create_user: RateLimitConf,
     /// Rate limits for the [`verify_user`] endpoint.
@@ -63,3 +161,101 @@ pub struct OprishRateLimits {
     /// Rate limits for the [`delete_session`] endpoint.
     pub delete_session: RateLimitConf,
 }
+
+impl OprishRateLimits {
+    /// Get the rate limit of an Oprish route.
+    pub fn get(&self, route: OprishRoute) -> &RateLimitConf {
+        match route {
+            OprishRoute::GetInstanceInfo => &self.get_instance_info,
+            OprishRoute::CreateMessage => &self.create_message,
+            OprishRoute::EditMessage => &self.edit_message,
+            OprishRoute::DeleteMessage => &self.delete_message,
+            OprishRoute::CreateUser => &self.create_user,
+            OprishRoute::VerifyUser => &self.verify_user,
+            OprishRoute::GetUser => &self.get_user,
+            OprishRoute::GuestGetUser => &self.guest_get_user,
+            OprishRoute::UpdateUser => &self.update_user,
+            OprishRoute::UpdateProfile => &self.update_profile,
+            OprishRoute::DeleteUser => &self.delete_user,
+            OprishRoute::CreatePasswordResetCode => &self.create_password_reset_code,
+            OprishRoute::ResetPassword => &self.reset_password,
+            OprishRoute::CreateSession => &self.create_session,
+            OprishRoute::GetSessions => &self.get_sessions,
+            OprishRoute::DeleteSession => &self.delete_session,
+        }
+    }
+}
+
+/// The Oprish routes that have their own rate limit.
+///
+/// This is a string in `snake_case`, matching the [`OprishRateLimits`] field names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OprishRoute {
+    GetInstanceInfo,
+    CreateMessage,
+    EditMessage,
+    DeleteMessage,
+    CreateUser,
+    VerifyUser,
+    GetUser,
+    GuestGetUser,
+    UpdateUser,
+    UpdateProfile,
+    DeleteUser,
+    CreatePasswordResetCode,
+    ResetPassword,
+    CreateSession,
+    GetSessions,
+    DeleteSession,
+}
+
+impl OprishRoute {
+    /// All the Oprish routes.
+    pub const ALL: [Self; 16] = [
+        Self::GetInstanceInfo,
+        Self::CreateMessage,
+        Self::EditMessage,
+        Self::DeleteMessage,
+        Self::CreateUser,
+        Self::VerifyUser,
+        Self::GetUser,
+        Self::GuestGetUser,
+        Self::UpdateUser,
+        Self::UpdateProfile,
+        Self::DeleteUser,
+        Self::CreatePasswordResetCode,
+        Self::ResetPassword,
+        Self::CreateSession,
+        Self::GetSessions,
+        Self::DeleteSession,
+    ];
+
+    /// Get the name of the route's [`OprishRateLimits`] field.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::GetInstanceInfo => "get_instance_info",
+            Self::CreateMessage => "create_message",
+            Self::EditMessage => "edit_message",
+            Self::DeleteMessage => "delete_message",
+            Self::CreateUser => "create_user",
+            Self::VerifyUser => "verify_user",
+            Self::GetUser => "get_user",
+            Self::GuestGetUser => "guest_get_user",
+            Self::UpdateUser => "update_user",
+            Self::UpdateProfile => "update_profile",
+            Self::DeleteUser => "delete_user",
+            Self::CreatePasswordResetCode => "create_password_reset_code",
+            Self::ResetPassword => "reset_password",
+            Self::CreateSession => "create_session",
+            Self::GetSessions => "get_sessions",
+            Self::DeleteSession => "delete_session",
+        }
+    }
+}
+
+impl fmt::Display for OprishRoute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
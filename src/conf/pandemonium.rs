@@ -1,10 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-use super::RateLimitConf;
+use super::{preset::pandemonium_rate_limit_default, RateLimitConf};
 
 /// Pandemonium configuration.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PandemoniumConf {
     pub url: String,
+    #[serde(default = "pandemonium_rate_limit_default")]
     pub rate_limit: RateLimitConf,
 }
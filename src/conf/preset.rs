@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{EffisRateLimitConf, EffisRateLimits, OprishRateLimits, RateLimitConf};
+
+/// A named set of rate limits that an instance can start off of.
+///
+/// Any rate limit that is explicitly configured overrides the preset's value.
+///
+/// Presets are only applied when loading a config with `Conf::new` or `Conf::from_toml`, which
+/// also fill in the missing fields of partially configured rate limits like in the example below.
+/// Byte budgets taken from the preset are raised to fit at least a single file of the instance's
+/// `file_size` and `attachment_file_size`.
+/// Deserializing a [`Conf`](super::Conf) directly ignores the preset, requires every configured
+/// rate limit to be complete and falls back to the [`RateLimitPreset::Public`] values for missing
+/// ones.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```toml
+/// rate_limit_preset = "strict"
+///
+/// [oprish.rate_limits.create_message]
+/// limit = 20 # reset_after is inherited from the preset
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitPreset {
+    /// Very lenient rate limits, meant for local development and testing.
+    Development,
+    /// Rate limits suitable for most public instances.
+    #[default]
+    Public,
+    /// Tighter rate limits for instances that are under heavy load or being abused.
+    Strict,
+}
+
+impl RateLimitPreset {
+    /// Scale a public rate limit according to the preset.
+    fn rate_limit(self, reset_after: u64, limit: u32) -> RateLimitConf {
+        let reset_after = Duration::from_secs(reset_after);
+        match self {
+            Self::Development => RateLimitConf {
+                reset_after,
+                limit: limit.saturating_mul(10),
+            },
+            Self::Public => RateLimitConf { reset_after, limit },
+            Self::Strict => RateLimitConf {
+                reset_after: reset_after.saturating_mul(2),
+                limit: (limit / 2).max(1),
+            },
+        }
+    }
+
+    /// Scale a public Effis rate limit according to the preset.
+    fn effis_rate_limit(
+        self,
+        reset_after: u64,
+        limit: u32,
+        file_size_limit: u64,
+    ) -> EffisRateLimitConf {
+        let rate_limit = self.rate_limit(reset_after, limit);
+        EffisRateLimitConf {
+            reset_after: rate_limit.reset_after,
+            limit: rate_limit.limit,
+            file_size_limit: match self {
+                Self::Development => file_size_limit.saturating_mul(10),
+                // Shrinking this could make it smaller than a single file.
+                Self::Public | Self::Strict => file_size_limit,
+            },
+        }
+    }
+
+    /// Get the preset's Oprish rate limits.
+    pub fn oprish_rate_limits(self) -> OprishRateLimits {
+        OprishRateLimits {
+            get_instance_info: self.rate_limit(5, 2),
+            create_message: self.rate_limit(5, 10),
+            edit_message: self.rate_limit(5, 5),
+            delete_message: self.rate_limit(5, 10),
+            create_user: self.rate_limit(360, 1),
+            verify_user: self.rate_limit(60, 2),
+            get_user: self.rate_limit(5, 255),
+            guest_get_user: self.rate_limit(5, 20),
+            update_user: self.rate_limit(180, 3),
+            update_profile: self.rate_limit(5, 5),
+            delete_user: self.rate_limit(1800, 1),
+            create_password_reset_code: self.rate_limit(60, 2),
+            reset_password: self.rate_limit(300, 5),
+            create_session: self.rate_limit(60, 5),
+            get_sessions: self.rate_limit(5, 2),
+            delete_session: self.rate_limit(5, 10),
+        }
+    }
+
+    /// Get the preset's Pandemonium rate limit.
+    pub fn pandemonium_rate_limit(self) -> RateLimitConf {
+        self.rate_limit(10, 5)
+    }
+
+    /// Get the preset's Effis rate limits.
+    pub fn effis_rate_limits(self) -> EffisRateLimits {
+        EffisRateLimits {
+            assets: self.effis_rate_limit(60, 5, 30_000_000),
+            attachments: self.effis_rate_limit(180, 20, 500_000_000),
+            fetch_file: self.rate_limit(60, 30),
+        }
+    }
+}
+
+impl Default for OprishRateLimits {
+    fn default() -> Self {
+        RateLimitPreset::default().oprish_rate_limits()
+    }
+}
+
+impl Default for EffisRateLimits {
+    fn default() -> Self {
+        RateLimitPreset::default().effis_rate_limits()
+    }
+}
+
+pub(crate) fn pandemonium_rate_limit_default() -> RateLimitConf {
+    RateLimitPreset::default().pandemonium_rate_limit()
+}
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ErrorResponse, SharedErrorData, UserCreate};
+
+/// Registration configuration.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```toml
+/// [oprish.registration]
+/// mode = "require_verification"
+/// denied_email_domains = ["tempmail.com"]
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistrationConf {
+    /// Who can create an account on the instance.
+    #[serde(default)]
+    pub mode: RegistrationMode,
+    /// The only email domains that can be used to create an account, an empty list allows all
+    /// domains.
+    ///
+    /// Domains also match their subdomains.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_email_domains: Vec<String>,
+    /// The email domains that can't be used to create an account.
+    ///
+    /// Domains also match their subdomains.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_email_domains: Vec<String>,
+}
+
+/// Who can create an account on an instance.
+///
+/// This is a string in `snake_case`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    /// Anyone can create an account.
+    #[default]
+    Open,
+    /// Only people with an invite code can create an account.
+    InviteOnly,
+    /// No one can create an account.
+    Closed,
+    /// Anyone can create an account but they have to verify their email before using it.
+    RequireVerification,
+}
+
+impl RegistrationConf {
+    /// Check whether a [`UserCreate`] is allowed to create an account.
+    ///
+    /// For invite-only instances this only checks that an invite code is present, validating it
+    /// is left to the caller.
+    pub fn check(&self, user: &UserCreate) -> Result<(), ErrorResponse> {
+        match self.mode {
+            RegistrationMode::Closed => {
+                return Err(forbidden(
+                    "This instance is not accepting new registrations",
+                ))
+            }
+            RegistrationMode::InviteOnly if user.invite_code.is_none() => {
+                return Err(forbidden("This instance requires an invite to register"))
+            }
+            _ => {}
+        }
+
+        let domain = match user.email.rsplit_once('@') {
+            Some((local, domain)) if !local.is_empty() && !domain.is_empty() => {
+                domain.to_lowercase()
+            }
+            _ => return Err(invalid_email("Invalid email address")),
+        };
+        if !self.allowed_email_domains.is_empty()
+            && !self
+                .allowed_email_domains
+                .iter()
+                .any(|allowed| domain_matches(&domain, allowed))
+        {
+            return Err(invalid_email(format!(
+                "Email addresses from {} are not allowed on this instance",
+                domain
+            )));
+        }
+        if self
+            .denied_email_domains
+            .iter()
+            .any(|denied| domain_matches(&domain, denied))
+        {
+            return Err(invalid_email(format!(
+                "Email addresses from {} are not allowed on this instance",
+                domain
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Whether new users have to verify their email before using their account.
+    pub fn requires_verification(&self) -> bool {
+        self.mode == RegistrationMode::RequireVerification
+    }
+}
+
+fn domain_matches(domain: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_start_matches('.');
+    domain.eq_ignore_ascii_case(pattern)
+        || domain
+            .strip_suffix(&pattern.to_lowercase())
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+}
+
+fn forbidden(message: &str) -> ErrorResponse {
+    ErrorResponse::Forbidden {
+        shared: SharedErrorData {
+            status: 403,
+            message: message.to_string(),
+        },
+    }
+}
+
+fn invalid_email<T: Into<String>>(info: T) -> ErrorResponse {
+    ErrorResponse::Validation {
+        shared: SharedErrorData {
+            status: 422,
+            message: "Invalid request".to_string(),
+        },
+        value_name: "email".to_string(),
+        info: info.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(email: &str, invite_code: Option<&str>) -> UserCreate {
+        UserCreate {
+            username: "yendri".to_string(),
+            email: email.to_string(),
+            password: "autentícame por favor".to_string(),
+            invite_code: invite_code.map(str::to_string),
+        }
+    }
+
+    fn is_forbidden(result: Result<(), ErrorResponse>) -> bool {
+        matches!(result, Err(ErrorResponse::Forbidden { .. }))
+    }
+
+    fn is_invalid_email(result: Result<(), ErrorResponse>) -> bool {
+        matches!(result, Err(ErrorResponse::Validation { value_name, .. }) if value_name == "email")
+    }
+
+    #[test]
+    fn modes() {
+        let mut conf = RegistrationConf::default();
+        assert_eq!(conf.check(&user("yendri@example.com", None)), Ok(()));
+
+        conf.mode = RegistrationMode::Closed;
+        assert!(is_forbidden(conf.check(&user("yendri@example.com", None))));
+        assert!(is_forbidden(
+            conf.check(&user("yendri@example.com", Some("invite")))
+        ));
+
+        conf.mode = RegistrationMode::InviteOnly;
+        assert!(is_forbidden(conf.check(&user("yendri@example.com", None))));
+        assert_eq!(
+            conf.check(&user("yendri@example.com", Some("invite"))),
+            Ok(())
+        );
+        // Invite codes don't let invalid emails through.
+        assert!(is_invalid_email(
+            conf.check(&user("yendri", Some("invite")))
+        ));
+
+        conf.mode = RegistrationMode::RequireVerification;
+        assert_eq!(conf.check(&user("yendri@example.com", None)), Ok(()));
+    }
+
+    #[test]
+    fn domain_matching() {
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(domain_matches("example.com", "Example.COM"));
+        assert!(domain_matches("mail.example.com", "example.com"));
+        assert!(domain_matches("a.b.example.com", ".example.com"));
+        assert!(!domain_matches("badexample.com", "example.com"));
+        assert!(!domain_matches("example.com", "mail.example.com"));
+        assert!(!domain_matches("example.com.evil", "example.com"));
+    }
+
+    #[test]
+    fn allowed_and_denied_domains() {
+        let conf = RegistrationConf {
+            allowed_email_domains: vec!["example.com".to_string()],
+            denied_email_domains: vec!["spam.example.com".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(conf.check(&user("yendri@example.com", None)), Ok(()));
+        assert_eq!(conf.check(&user("yendri@EXAMPLE.com", None)), Ok(()));
+        assert_eq!(conf.check(&user("yendri@mail.example.com", None)), Ok(()));
+        assert!(is_invalid_email(
+            conf.check(&user("yendri@eludris.gay", None))
+        ));
+        // Denied domains win over allowed ones.
+        assert!(is_invalid_email(
+            conf.check(&user("yendri@spam.example.com", None))
+        ));
+        assert!(is_invalid_email(
+            conf.check(&user("yendri@very.spam.example.com", None))
+        ));
+
+        // Without allowed domains everything but the denied ones is allowed.
+        let conf = RegistrationConf {
+            denied_email_domains: vec!["tempmail.com".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(conf.check(&user("yendri@eludris.gay", None)), Ok(()));
+        assert!(is_invalid_email(
+            conf.check(&user("yendri@tempmail.com", None))
+        ));
+        assert!(is_invalid_email(conf.check(&user("@tempmail.com", None))));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{env, fmt, fs, path::PathBuf};
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The placeholder that secrets are replaced with when they are formatted or serialized.
+const REDACTED: &str = "[REDACTED]";
+
+/// Secret configuration values.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```toml
+/// [secrets]
+/// database_url = { env = "DATABASE_URL" }
+/// session_secret = { file = "/run/secrets/session_secret" }
+///
+/// [secrets.smtp]
+/// username = "eludris"
+/// password = { env = "SMTP_PASSWORD" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecretsConf {
+    /// The URL of the instance's database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database_url: Option<Secret>,
+    /// The secret used to sign session tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_secret: Option<Secret>,
+    /// The credentials used to authenticate with the instance's SMTP relay.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<SmtpCredentials>,
+}
+
+/// The credentials used to authenticate with an SMTP relay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmtpCredentials {
+    pub username: Secret,
+    pub password: Secret,
+}
+
+/// A configuration value that must never be leaked.
+///
+/// The value can either be provided directly, read from a file or read from an environment
+/// variable. Trailing newlines are stripped from values read from files.
+///
+/// Both the [`Debug`] and [`Serialize`] implementations output `[REDACTED]` instead of the actual
+/// value, use [`Secret::expose`] to access it. Deserializing `[REDACTED]` fails so that a
+/// serialized config can't be read back with its secrets silently replaced.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```toml
+/// direct = "hunter2"
+/// from_file = { file = "/run/secrets/some_secret" }
+/// from_env = { env = "SOME_SECRET" }
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Create a new [`Secret`] from its value.
+    pub fn new<T: Into<String>>(value: T) -> Self {
+        Self(value.into())
+    }
+
+    /// Get the secret's actual value.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SecretSource {
+    Value(String),
+    File { file: PathBuf },
+    Env { env: String },
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match SecretSource::deserialize(deserializer)? {
+            SecretSource::Value(value) if value == REDACTED => Err(D::Error::custom(
+                "Secret is redacted, the actual value has to be provided",
+            )),
+            SecretSource::Value(value) => Ok(Self(value)),
+            SecretSource::File { file } => fs::read_to_string(&file)
+                .map(|value| Self(value.trim_end_matches(['\n', '\r']).to_string()))
+                .map_err(|err| {
+                    D::Error::custom(format!(
+                        "Could not read secret file {}: {}",
+                        file.display(),
+                        err
+                    ))
+                }),
+            SecretSource::Env { env } => env::var(&env).map(Self).map_err(|err| {
+                D::Error::custom(format!(
+                    "Could not read secret environment variable {}: {}",
+                    env, err
+                ))
+            }),
+        }
+    }
+}
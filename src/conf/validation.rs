@@ -0,0 +1,265 @@
+use std::{error::Error, fmt, time::Duration};
+
+use url::Url;
+
+use super::{Conf, EffisRateLimitConf, OprishRoute, RateLimitConf};
+
+/// A single problem found while validating a [`Conf`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfError {
+    /// The dotted path of the offending field, for example `oprish.rate_limits.create_message`.
+    pub path: String,
+    /// A brief explanation of what is wrong with the field.
+    pub message: String,
+}
+
+impl fmt::Display for ConfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Every problem found while validating a [`Conf`].
+///
+/// This is never empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfErrors(pub Vec<ConfError>);
+
+impl fmt::Display for ConfErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid config")?;
+        for error in self.0.iter() {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfErrors {}
+
+impl Conf {
+    /// Check that the config's values make sense together.
+    ///
+    /// Every problem is reported at once, each with the dotted path of the offending field.
+    pub fn validate(&self) -> Result<(), ConfErrors> {
+        let mut validator = Validator::default();
+
+        validator.length("instance_name", &self.instance_name, 1, 32);
+        if let Some(description) = &self.description {
+            validator.length("description", description, 1, 2048);
+        }
+        if let Some(email_address) = &self.email_address {
+            validator.email("email_address", email_address);
+        }
+
+        validator.url("oprish.url", &self.oprish.url, &["http", "https"]);
+        validator.non_zero("oprish.message_limit", self.oprish.message_limit as u64);
+        validator.non_zero("oprish.bio_limit", self.oprish.bio_limit as u64);
+        for route in OprishRoute::ALL {
+            validator.rate_limit(
+                &format!("oprish.rate_limits.{}", route),
+                self.oprish.rate_limits.get(route),
+            );
+        }
+
+        for (index, tier) in self.oprish.rate_limit_tiers.iter().enumerate() {
+            let path = format!("oprish.rate_limit_tiers.{}", index);
+            if tier.name.is_empty() {
+                validator.error(&format!("{}.name", path), "Can't be empty");
+            }
+            for (route, rate_limit) in tier.rate_limits.iter() {
+                validator.rate_limit(&format!("{}.rate_limits.{}", path, route), rate_limit);
+            }
+        }
+
+        let registration = &self.oprish.registration;
+        if registration.requires_verification() && self.email.is_none() {
+            validator.error(
+                "oprish.registration.mode",
+                "Requiring verification needs the email section to be configured",
+            );
+        }
+        for (name, domains) in [
+            ("allowed_email_domains", &registration.allowed_email_domains),
+            ("denied_email_domains", &registration.denied_email_domains),
+        ] {
+            for (index, domain) in domains.iter().enumerate() {
+                if domain.is_empty() || domain.contains('@') {
+                    validator.error(
+                        &format!("oprish.registration.{}.{}", name, index),
+                        "Must be a valid domain",
+                    );
+                }
+            }
+        }
+
+        validator.url("pandemonium.url", &self.pandemonium.url, &["ws", "wss"]);
+        validator.rate_limit("pandemonium.rate_limit", &self.pandemonium.rate_limit);
+
+        let effis = &self.effis;
+        validator.url("effis.url", &effis.url, &["http", "https"]);
+        validator.non_zero("effis.file_size", effis.file_size);
+        validator.non_zero("effis.attachment_file_size", effis.attachment_file_size);
+        if effis.file_size > effis.attachment_file_size {
+            validator.error(
+                "effis.file_size",
+                "Can't be larger than effis.attachment_file_size",
+            );
+        }
+        validator.effis_rate_limit(
+            "effis.rate_limits.assets",
+            &effis.rate_limits.assets,
+            effis.file_size,
+        );
+        validator.effis_rate_limit(
+            "effis.rate_limits.attachments",
+            &effis.rate_limits.attachments,
+            effis.attachment_file_size,
+        );
+        validator.rate_limit(
+            "effis.rate_limits.fetch_file",
+            &effis.rate_limits.fetch_file,
+        );
+
+        if let Some(email) = &self.email {
+            if email.relay.is_empty() {
+                validator.error("email.relay", "Can't be empty");
+            }
+            if let Some(name) = &email.name {
+                validator.length("email.name", name, 1, 64);
+            }
+            validator.email("email.address", &email.address);
+        }
+
+        if let Some(session_secret) = &self.secrets.session_secret {
+            if session_secret.expose().len() < 32 {
+                validator.error("secrets.session_secret", "Must be at least 32 bytes long");
+            }
+        }
+
+        validator.finish()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Validator {
+    errors: Vec<ConfError>,
+}
+
+impl Validator {
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.errors.push(ConfError {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn non_zero(&mut self, path: &str, value: u64) {
+        if value == 0 {
+            self.error(path, "Can't be 0");
+        }
+    }
+
+    fn duration(&mut self, path: &str, value: Duration) {
+        if value.as_millis() == 0 {
+            self.error(path, "Must be at least 1 millisecond");
+        }
+    }
+
+    fn length(&mut self, path: &str, value: &str, min: usize, max: usize) {
+        let length = value.chars().count();
+        if length < min || length > max {
+            self.error(
+                path,
+                format!("Must be between {} and {} characters long", min, max),
+            );
+        }
+    }
+
+    fn email(&mut self, path: &str, value: &str) {
+        match value.split_once('@') {
+            Some((local, domain)) if !local.is_empty() && !domain.is_empty() => {}
+            _ => self.error(path, "Must be a valid email address"),
+        }
+    }
+
+    fn url(&mut self, path: &str, value: &str, schemes: &[&str]) {
+        match Url::parse(value) {
+            Ok(url) if schemes.contains(&url.scheme()) => {}
+            Ok(url) => self.error(
+                path,
+                format!(
+                    "Invalid URL scheme {}, expected one of {}",
+                    url.scheme(),
+                    schemes.join(", ")
+                ),
+            ),
+            Err(err) => self.error(path, format!("Invalid URL: {}", err)),
+        }
+    }
+
+    fn rate_limit(&mut self, path: &str, rate_limit: &RateLimitConf) {
+        self.duration(&format!("{}.reset_after", path), rate_limit.reset_after);
+        self.non_zero(&format!("{}.limit", path), rate_limit.limit as u64);
+    }
+
+    fn effis_rate_limit(&mut self, path: &str, rate_limit: &EffisRateLimitConf, file_size: u64) {
+        self.duration(&format!("{}.reset_after", path), rate_limit.reset_after);
+        self.non_zero(&format!("{}.limit", path), rate_limit.limit as u64);
+        if rate_limit.file_size_limit < file_size {
+            self.error(
+                &format!("{}.file_size_limit", path),
+                format!("Can't be smaller than a single file ({} bytes)", file_size),
+            );
+        }
+    }
+
+    fn finish(self) -> Result<(), ConfErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfErrors(self.errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+
+    #[test]
+    fn reports_every_error() {
+        let mut conf = fixtures::conf();
+        assert_eq!(conf.validate(), Ok(()));
+
+        conf.oprish.url = "ftp://example.com".to_string();
+        conf.oprish.rate_limits.create_message.limit = 0;
+        conf.effis.file_size = 30_000_000;
+        conf.effis.rate_limits.assets.file_size_limit = 10_000_000;
+
+        let errors = conf.validate().unwrap_err().0;
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "oprish.url",
+                "oprish.rate_limits.create_message.limit",
+                "effis.file_size",
+                "effis.rate_limits.assets.file_size_limit",
+            ]
+        );
+        assert_eq!(
+            errors[0].message,
+            "Invalid URL scheme ftp, expected one of http, https"
+        );
+        assert_eq!(errors[1].message, "Can't be 0");
+        assert_eq!(
+            errors[2].message,
+            "Can't be larger than effis.attachment_file_size"
+        );
+        assert_eq!(
+            errors[3].message,
+            "Can't be smaller than a single file (30000000 bytes)"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{Conf, ConfChange};
+
+/// Keeps track of an Eludris config file and reloads it when it changes.
+///
+/// The watcher does not spawn anything on its own, services are expected to call
+/// [`ConfWatcher::poll`] periodically (or on `SIGHUP`) and apply the returned changes.
+#[derive(Debug, Clone)]
+pub struct ConfWatcher {
+    path: PathBuf,
+    conf: Conf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfWatcher {
+    /// Create a new [`ConfWatcher`] by loading the config file at `path`.
+    pub fn new<T: AsRef<Path>>(path: T) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified(&path);
+        let conf = Conf::new(&path)?;
+        Ok(Self {
+            path,
+            conf,
+            modified,
+        })
+    }
+
+    /// Create a new [`ConfWatcher`] for the config file pointed to by the `ELUDRIS_CONF`
+    /// environment variable, falling back to `Eludris.toml` if it is not set.
+    pub fn new_from_env() -> anyhow::Result<Self> {
+        Self::new(super::loader::conf_path_from_env())
+    }
+
+    /// The currently loaded config.
+    pub fn conf(&self) -> &Conf {
+        &self.conf
+    }
+
+    /// The path of the watched config file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reload the config if the file was modified since it was last loaded.
+    ///
+    /// Returns the changes that were applied, which is empty if the file was not modified.
+    pub fn poll(&mut self) -> anyhow::Result<Vec<ConfChange>> {
+        let modified = modified(&self.path);
+        if modified.is_some() && modified == self.modified {
+            return Ok(vec![]);
+        }
+        self.reload()
+    }
+
+    /// Unconditionally reload the config.
+    ///
+    /// If the new config fails to load or validate the current one is kept and the error is
+    /// returned, [`ConfWatcher::poll`] won't try again until the file is modified. Otherwise the
+    /// changes between the two are returned.
+    pub fn reload(&mut self) -> anyhow::Result<Vec<ConfChange>> {
+        self.modified = modified(&self.path);
+        let conf = Conf::new(&self.path)?;
+        let changes = self.conf.diff(&conf);
+        self.conf = conf;
+        Ok(changes)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,307 @@
+use anyhow::Context;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::{Session, User};
+use crate::conf::{Conf, SmtpTls};
+
+/// The emails an Eludris instance sends to its users.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmailTemplate {
+    /// The email sent to verify a user's email address.
+    Verification {
+        /// The code the user has to pass to the [`verify_user`] route.
+        code: u32,
+    },
+    /// The email sent when a user asks for a password reset code.
+    PasswordReset {
+        /// The code the user has to pass in their [`ResetPassword`](crate::ResetPassword).
+        code: u32,
+    },
+    /// The email sent when a new session is created for a user.
+    NewLogin {
+        /// The newly created session.
+        session: Session,
+    },
+}
+
+/// A rendered [`EmailTemplate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedEmail {
+    /// The email's subject.
+    pub subject: String,
+    /// The email's plain text body.
+    pub text: String,
+    /// The email's HTML body.
+    pub html: String,
+}
+
+impl EmailTemplate {
+    /// Render the email for a `user` of the instance named `instance_name`.
+    pub fn render(&self, instance_name: &str, user: &User) -> RenderedEmail {
+        let (subject, lines) = match self {
+            Self::Verification { code } => (
+                format!("Verify your {} account", instance_name),
+                vec![
+                    format!("Welcome to {}!", instance_name),
+                    format!("Your verification code is {:06}.", code),
+                    "If you didn't create an account you can safely ignore this email.".to_string(),
+                ],
+            ),
+            Self::PasswordReset { code } => (
+                format!("Reset your {} password", instance_name),
+                vec![
+                    format!("Your password reset code is {:06}.", code),
+                    "If you didn't request a password reset you can safely ignore this email."
+                        .to_string(),
+                ],
+            ),
+            Self::NewLogin { session } => (
+                format!("New login to your {} account", instance_name),
+                vec![
+                    format!(
+                        "Someone logged into your account using {} on {} from {}.",
+                        session.client, session.platform, session.ip
+                    ),
+                    "If this wasn't you, change your password and delete the session right away."
+                        .to_string(),
+                ],
+            ),
+        };
+        let greeting = format!("Hi {},", user);
+
+        let text = format!("{}\n\n{}\n", greeting, lines.join("\n\n"));
+        let html = format!(
+            "<!DOCTYPE html>\n<html>\n<body>\n<p>{}</p>\n{}\n</body>\n</html>\n",
+            escape_html(&greeting),
+            lines
+                .iter()
+                .map(|line| format!("<p>{}</p>", escape_html(line)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        RenderedEmail {
+            subject,
+            text,
+            html,
+        }
+    }
+}
+
+impl RenderedEmail {
+    /// Build a multipart plain text and HTML message out of the email.
+    pub fn message(&self, from: Mailbox, to: Mailbox) -> anyhow::Result<Message> {
+        Message::builder()
+            .from(from)
+            .to(to)
+            .subject(&self.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                self.text.clone(),
+                self.html.clone(),
+            ))
+            .context("Could not build email")
+    }
+}
+
+/// Sends [`EmailTemplate`]s through the instance's SMTP relay.
+#[derive(Debug, Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    instance_name: String,
+}
+
+impl Mailer {
+    /// Create a new [`Mailer`] from the instance's [`Conf`].
+    ///
+    /// This fails if the instance has no email configuration.
+    pub fn new(conf: &Conf) -> anyhow::Result<Self> {
+        let email = conf
+            .email
+            .as_ref()
+            .context("The instance has no email configuration")?;
+
+        let builder = match email.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&email.relay),
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&email.relay)?
+            }
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&email.relay)?,
+        }
+        .port(email.port());
+        let transport = match &conf.secrets.smtp {
+            Some(smtp) => builder.credentials(Credentials::new(
+                smtp.username.expose().to_string(),
+                smtp.password.expose().to_string(),
+            )),
+            None => builder,
+        }
+        .build();
+
+        let from = Mailbox::new(
+            Some(email.name.as_ref().unwrap_or(&conf.instance_name).clone()),
+            email.address.parse().context("Invalid email address")?,
+        );
+
+        Ok(Self {
+            transport,
+            from,
+            instance_name: conf.instance_name.clone(),
+        })
+    }
+
+    /// Render and send an email to a `user`.
+    ///
+    /// The user's `email` field has to be present.
+    pub async fn send(&self, user: &User, template: &EmailTemplate) -> anyhow::Result<()> {
+        let address = user
+            .email
+            .as_ref()
+            .context("The user has no email address")?
+            .parse()
+            .context("The user has an invalid email address")?;
+        let message = template
+            .render(&self.instance_name, user)
+            .message(self.from.clone(), Mailbox::new(None, address))?;
+        self.transport
+            .send(message)
+            .await
+            .context("Could not send email")?;
+        Ok(())
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use lettre::message::Mailbox;
+
+    use super::*;
+    use crate::fixtures::{self, user};
+
+    #[test]
+    fn render_verification() {
+        let email = EmailTemplate::Verification { code: 42 }.render("Eludris", &user(1));
+
+        assert_eq!(email.subject, "Verify your Eludris account");
+        assert_eq!(
+            email.text,
+            "Hi yendri,\n\n\
+             Welcome to Eludris!\n\n\
+             Your verification code is 000042.\n\n\
+             If you didn't create an account you can safely ignore this email.\n"
+        );
+        assert_eq!(
+            email.html,
+            "<!DOCTYPE html>\n<html>\n<body>\n\
+             <p>Hi yendri,</p>\n\
+             <p>Welcome to Eludris!</p>\n\
+             <p>Your verification code is 000042.</p>\n\
+             <p>If you didn&#39;t create an account you can safely ignore this email.</p>\n\
+             </body>\n</html>\n"
+        );
+    }
+
+    #[test]
+    fn render_pads_codes() {
+        let user = user(1);
+
+        let email = EmailTemplate::PasswordReset { code: 7 }.render("Eludris", &user);
+        assert!(email.text.contains("Your password reset code is 000007."));
+
+        let email = EmailTemplate::PasswordReset { code: 123456 }.render("Eludris", &user);
+        assert!(email.text.contains("Your password reset code is 123456."));
+    }
+
+    #[test]
+    fn render_new_login() {
+        let session = Session {
+            id: 2.into(),
+            user_id: 1.into(),
+            platform: "linux".to_string(),
+            client: "pilfer".to_string(),
+            ip: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+        };
+        let email = EmailTemplate::NewLogin { session }.render("Eludris", &user(1));
+
+        assert_eq!(email.subject, "New login to your Eludris account");
+        assert!(email
+            .text
+            .contains("Someone logged into your account using pilfer on linux from 127.0.0.1."));
+    }
+
+    #[test]
+    fn render_escapes_display_name() {
+        let user = User {
+            display_name: Some("<b>\"Tom\" & 'Jerry'</b>".to_string()),
+            ..user(1)
+        };
+        let email = EmailTemplate::Verification { code: 1 }.render("Eludris", &user);
+
+        assert!(email.text.starts_with("Hi <b>\"Tom\" & 'Jerry'</b>,\n"));
+        assert!(email
+            .html
+            .contains("<p>Hi &lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;,</p>"));
+        assert!(!email.html.contains("<b>"));
+    }
+
+    #[test]
+    fn message_is_multipart() {
+        let email = EmailTemplate::Verification { code: 42 }.render("Eludris", &user(1));
+        let from: Mailbox = "Eludris <noreply@eludris.gay>".parse().unwrap();
+        let to: Mailbox = "yendri@llamoyendri.io".parse().unwrap();
+        let message = String::from_utf8(email.message(from, to).unwrap().formatted()).unwrap();
+
+        assert!(message.contains("From: Eludris <noreply@eludris.gay>\r\n"));
+        assert!(message.contains("To: yendri@llamoyendri.io\r\n"));
+        assert!(message.contains("Subject: Verify your Eludris account\r\n"));
+        assert!(message.contains("Content-Type: multipart/alternative;"));
+        assert!(message.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(message.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        assert!(message.contains("Your verification code is 000042."));
+        // The plain text alternative has to come first for clients to prefer the HTML one.
+        assert!(message.find("text/plain").unwrap() < message.find("text/html").unwrap());
+    }
+
+    /// Sends an email through an SMTP sink like `python -m smtpd -n -c DebuggingServer` or
+    /// Mailpit listening on port 1025.
+    #[tokio::test]
+    #[ignore = "requires an SMTP sink on localhost:1025"]
+    async fn mailer_sends_to_sink() {
+        let conf = fixtures::conf_with(
+            r#"
+[email]
+relay = "localhost"
+port = 1025
+tls = "none"
+address = "noreply@eludris.gay"
+"#,
+        );
+        let mailer = Mailer::new(&conf).unwrap();
+
+        mailer
+            .send(&user(1), &EmailTemplate::Verification { code: 42 })
+            .await
+            .unwrap();
+    }
+}
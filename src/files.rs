@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::Id;
+
 /// Represents a file stored on Effis.
 ///
 /// -----
@@ -21,7 +23,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileData {
     /// The file's ID.
-    pub id: u64,
+    pub id: Id,
     /// The file's name.
     pub name: String,
     /// The bucket the file is stored in.
@@ -0,0 +1,94 @@
+//! Fixtures shared by the crate's tests.
+
+use std::time::Duration;
+
+#[cfg(feature = "logic")]
+use crate::conf::Conf;
+use crate::{
+    conf::{RateLimitConf, RateLimitPreset},
+    InstanceInfo, InstanceRateLimits, ServerPayload, Status, StatusType, User,
+};
+
+/// A minimal `Eludris.toml` with only the required fields set.
+///
+/// Other tables, or subtables like `[oprish.registration]`, can be appended to it.
+#[cfg(feature = "logic")]
+pub(crate) const CONF: &str = r#"
+instance_name = "WooChat"
+
+[oprish]
+url = "https://example.com"
+message_limit = 2000
+bio_limit = 250
+
+[pandemonium]
+url = "wss://example.com"
+
+[effis]
+url = "https://cdn.example.com"
+file_size = "20MB"
+attachment_file_size = "25MB"
+"#;
+
+/// Load [`CONF`] without any overrides.
+#[cfg(feature = "logic")]
+pub(crate) fn conf() -> Conf {
+    conf_with("")
+}
+
+/// Load [`CONF`] with `extra` appended to it.
+#[cfg(feature = "logic")]
+pub(crate) fn conf_with(extra: &str) -> Conf {
+    Conf::from_toml(&format!("{}{}", CONF, extra), []).unwrap()
+}
+
+/// A verified user without any of the optional profile fields.
+pub(crate) fn user(id: u64) -> User {
+    User {
+        id: id.into(),
+        username: "yendri".to_string(),
+        display_name: None,
+        social_credit: 0,
+        status: Status {
+            status_type: StatusType::Online,
+            text: None,
+        },
+        bio: None,
+        avatar: None,
+        banner: None,
+        badges: 0,
+        permissions: 0,
+        email: Some("yendri@llamoyendri.io".to_string()),
+        verified: Some(true),
+    }
+}
+
+/// A `HELLO` payload from an instance using the public rate limit preset.
+pub(crate) fn hello(heartbeat_interval: u64) -> ServerPayload {
+    let preset = RateLimitPreset::Public;
+    ServerPayload::Hello {
+        heartbeat_interval,
+        instance_info: Box::new(InstanceInfo {
+            instance_name: "EmreLand".to_string(),
+            description: None,
+            version: "0.3.3".to_string(),
+            message_limit: 2048,
+            bio_limit: 250,
+            oprish_url: "https://example.com".to_string(),
+            pandemonium_url: "https://example.com".to_string(),
+            effis_url: "https://example.com".to_string(),
+            file_size: 20_000_000,
+            attachment_file_size: 100_000_000,
+            email_address: Some("admin@example.com".to_string()),
+            rate_limits: Some(InstanceRateLimits {
+                oprish: preset.oprish_rate_limits(),
+                pandemonium: preset.pandemonium_rate_limit(),
+                effis: preset.effis_rate_limits(),
+            }),
+        }),
+        rate_limit: RateLimitConf {
+            reset_after: Duration::from_millis(10_500),
+            limit: 5,
+        },
+    }
+}
@@ -0,0 +1,594 @@
+use super::{ClientPayload, GatewayEvent, ReconnectPolicy, ServerPayload};
+use crate::Id;
+
+/// The delay before the first reconnect attempt in milliseconds.
+const BACKOFF_BASE: u64 = 1_000;
+/// The maximum delay between reconnect attempts in milliseconds.
+const BACKOFF_MAX: u64 = 60_000;
+
+/// The state of a [`GatewayClient`]'s connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GatewayState {
+    /// The client isn't connected and isn't trying to.
+    Disconnected,
+    /// The client is waiting to reconnect.
+    WaitingToReconnect,
+    /// The client is connecting and waiting for the `HELLO` payload.
+    Connecting,
+    /// The client sent an `AUTHENTICATE` or `RESUME` payload and is waiting for the response.
+    Authenticating,
+    /// The client is authenticated and receiving events.
+    Connected,
+    /// The client gave up reconnecting.
+    GaveUp,
+}
+
+/// Something a [`GatewayClient`]'s transport has to do.
+#[derive(Debug, Clone)]
+pub enum GatewayAction {
+    /// Open a new connection to Pandemonium.
+    Connect,
+    /// Send a payload over the current connection.
+    Send(ClientPayload),
+    /// Close the current connection.
+    ///
+    /// The connection should not be closed with the normal closure close code so that the
+    /// session can be resumed.
+    Close,
+    /// Pass a payload on to the application.
+    Event(Box<ServerPayload>),
+    /// The client gave up reconnecting, the application should not try again with the same
+    /// token.
+    GiveUp,
+}
+
+/// A gateway client.
+///
+/// The client implements the Pandemonium protocol: it authenticates after the `HELLO` payload,
+/// sends its first ping after `RAND * heartbeat_interval` milliseconds and keeps pinging every
+/// `heartbeat_interval`, reconnects when a `PONG` is missed or the connection is closed, backing
+/// off exponentially, and resumes its session when it can.
+///
+/// Transports feed it the payloads they receive, close events and timer ticks and carry out the
+/// [`GatewayAction`]s it returns.
+#[derive(Debug, Clone)]
+pub struct GatewayClient {
+    token: String,
+    state: GatewayState,
+    rng: Rng,
+    heartbeat_interval: u64,
+    next_ping: Option<u64>,
+    awaiting_pong: bool,
+    session: Option<Id>,
+    seq: u64,
+    attempts: u32,
+    reconnect_at: Option<u64>,
+    rate_limited_until: u64,
+}
+
+impl GatewayClient {
+    /// Create a new [`GatewayClient`] that authenticates with a session token.
+    ///
+    /// `seed` seeds the jitter of the first ping and the reconnect backoff, it should be random
+    /// outside of tests.
+    pub fn new<T: Into<String>>(token: T, seed: u64) -> Self {
+        Self {
+            token: token.into(),
+            state: GatewayState::Disconnected,
+            rng: Rng(seed),
+            heartbeat_interval: 0,
+            next_ping: None,
+            awaiting_pong: false,
+            session: None,
+            seq: 0,
+            attempts: 0,
+            reconnect_at: None,
+            rate_limited_until: 0,
+        }
+    }
+
+    /// Get the state of the client's connection.
+    pub fn state(&self) -> GatewayState {
+        self.state
+    }
+
+    /// Get the ID of the client's gateway session, if it has one.
+    pub fn session(&self) -> Option<Id> {
+        self.session
+    }
+
+    /// Get the sequence number of the last event the client received.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Whether the client is rate limited at `now`, in which case the application shouldn't send
+    /// any payloads.
+    pub fn is_rate_limited(&self, now: u64) -> bool {
+        self.rate_limited_until > now
+    }
+
+    /// Start connecting to Pandemonium.
+    pub fn connect(&mut self) -> Vec<GatewayAction> {
+        self.attempts = 0;
+        self.reconnect_at = None;
+        self.state = GatewayState::Connecting;
+        vec![GatewayAction::Connect]
+    }
+
+    /// Handle a payload received at `now`.
+    pub fn handle_event(&mut self, event: GatewayEvent, now: u64) -> Vec<GatewayAction> {
+        if let Some(seq) = event.seq {
+            self.seq = seq;
+        }
+
+        match event.payload {
+            ServerPayload::Hello {
+                heartbeat_interval, ..
+            } => {
+                self.heartbeat_interval = heartbeat_interval;
+                self.next_ping =
+                    Some(now + (self.rng.next_f64() * heartbeat_interval as f64) as u64);
+                self.awaiting_pong = false;
+                self.state = GatewayState::Authenticating;
+                let payload = match self.session {
+                    Some(session) => ClientPayload::Resume {
+                        token: self.token.clone(),
+                        session,
+                        seq: self.seq,
+                    },
+                    None => ClientPayload::Authenticate(self.token.clone()),
+                };
+                vec![
+                    GatewayAction::Send(payload),
+                    GatewayAction::Event(Box::new(event.payload)),
+                ]
+            }
+            ServerPayload::Pong => {
+                self.awaiting_pong = false;
+                vec![]
+            }
+            ServerPayload::Authenticated { session_id, .. } => {
+                self.session = Some(session_id);
+                self.seq = 0;
+                self.attempts = 0;
+                self.state = GatewayState::Connected;
+                vec![GatewayAction::Event(Box::new(event.payload))]
+            }
+            ServerPayload::Resumed => {
+                self.attempts = 0;
+                self.state = GatewayState::Connected;
+                vec![GatewayAction::Event(Box::new(event.payload))]
+            }
+            ServerPayload::InvalidSession => {
+                self.session = None;
+                self.seq = 0;
+                vec![
+                    GatewayAction::Send(ClientPayload::Authenticate(self.token.clone())),
+                    GatewayAction::Event(Box::new(event.payload)),
+                ]
+            }
+            ServerPayload::RateLimit { wait } => {
+                self.rate_limited_until = now + wait;
+                vec![GatewayAction::Event(Box::new(event.payload))]
+            }
+            payload => vec![GatewayAction::Event(Box::new(payload))],
+        }
+    }
+
+    /// Handle the connection being closed at `now`, with the close code if there is one.
+    ///
+    /// This should also be called when connecting fails.
+    pub fn handle_close(&mut self, code: Option<u16>, now: u64) -> Vec<GatewayAction> {
+        if matches!(
+            self.state,
+            GatewayState::Disconnected | GatewayState::WaitingToReconnect | GatewayState::GaveUp
+        ) {
+            return vec![];
+        }
+
+        match code.map_or(ReconnectPolicy::Resume, ReconnectPolicy::from_code) {
+            ReconnectPolicy::Resume => {}
+            ReconnectPolicy::Reconnect => {
+                self.session = None;
+                self.seq = 0;
+            }
+            ReconnectPolicy::GiveUp => {
+                self.disconnect();
+                self.state = GatewayState::GaveUp;
+                return vec![GatewayAction::GiveUp];
+            }
+        }
+        self.schedule_reconnect(now);
+        vec![]
+    }
+
+    /// Advance the client's timers to `now`.
+    pub fn tick(&mut self, now: u64) -> Vec<GatewayAction> {
+        if self
+            .reconnect_at
+            .is_some_and(|reconnect_at| reconnect_at <= now)
+        {
+            self.reconnect_at = None;
+            self.state = GatewayState::Connecting;
+            return vec![GatewayAction::Connect];
+        }
+
+        match self.next_ping {
+            Some(next_ping) if next_ping <= now => {
+                if self.awaiting_pong {
+                    // The server didn't answer the last ping, the connection is most likely dead.
+                    self.schedule_reconnect(now);
+                    return vec![GatewayAction::Close];
+                }
+                self.awaiting_pong = true;
+                self.next_ping = Some(now + self.heartbeat_interval);
+                vec![GatewayAction::Send(ClientPayload::Ping)]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Get when [`GatewayClient::tick`] should be called next, if at all.
+    pub fn next_tick(&self) -> Option<u64> {
+        match (self.reconnect_at, self.next_ping) {
+            (Some(reconnect_at), _) => Some(reconnect_at),
+            (None, next_ping) => next_ping,
+        }
+    }
+
+    /// Stop the client, forgetting its session.
+    pub fn close(&mut self) -> Vec<GatewayAction> {
+        let connected = !matches!(
+            self.state,
+            GatewayState::Disconnected | GatewayState::WaitingToReconnect | GatewayState::GaveUp
+        );
+        self.disconnect();
+        self.session = None;
+        self.seq = 0;
+        self.state = GatewayState::Disconnected;
+        if connected {
+            vec![GatewayAction::Close]
+        } else {
+            vec![]
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.next_ping = None;
+        self.awaiting_pong = false;
+        self.reconnect_at = None;
+    }
+
+    fn schedule_reconnect(&mut self, now: u64) {
+        self.disconnect();
+        let backoff = BACKOFF_BASE
+            .saturating_mul(1 << self.attempts.min(16))
+            .min(BACKOFF_MAX);
+        // Add up to 50% of jitter so that clients don't all reconnect at once.
+        let jitter = (self.rng.next_f64() * backoff as f64 / 2.0) as u64;
+        self.reconnect_at = Some(now.max(self.rate_limited_until) + backoff / 2 + jitter);
+        self.attempts = self.attempts.saturating_add(1);
+        self.state = GatewayState::WaitingToReconnect;
+    }
+}
+
+/// A small seedable PRNG (SplitMix64), good enough for jitter.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Get a random number in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, CloseCode};
+
+    const SEED: u64 = 0x5EED;
+    const HEARTBEAT_INTERVAL: u64 = 45_000;
+
+    fn client() -> GatewayClient {
+        let mut client = GatewayClient::new("token", SEED);
+        assert!(matches!(
+            client.connect().as_slice(),
+            [GatewayAction::Connect]
+        ));
+        assert_eq!(client.state(), GatewayState::Connecting);
+        client
+    }
+
+    fn event(client: &mut GatewayClient, payload: ServerPayload, now: u64) -> Vec<GatewayAction> {
+        client.handle_event(payload.into(), now)
+    }
+
+    /// Connect a client and authenticate it at 0, with a session ID of 1.
+    fn connected() -> GatewayClient {
+        let mut client = client();
+        event(&mut client, fixtures::hello(HEARTBEAT_INTERVAL), 0);
+        let actions = event(
+            &mut client,
+            ServerPayload::Authenticated {
+                user: fixtures::user(2),
+                users: vec![],
+                session_id: 1.into(),
+            },
+            0,
+        );
+        assert!(matches!(
+            actions.as_slice(),
+            [GatewayAction::Event(payload)] if matches!(**payload, ServerPayload::Authenticated { .. })
+        ));
+        assert_eq!(client.state(), GatewayState::Connected);
+        assert_eq!(client.session(), Some(1.into()));
+        client
+    }
+
+    /// Get the delay of the reconnect the client scheduled at `now`, checking that it's between
+    /// half of `backoff` and `backoff`.
+    fn reconnect_delay(client: &GatewayClient, now: u64, backoff: u64) -> u64 {
+        assert_eq!(client.state(), GatewayState::WaitingToReconnect);
+        let delay = client.next_tick().unwrap() - now;
+        assert!(
+            (backoff / 2..backoff).contains(&delay),
+            "{} isn't within a {} backoff",
+            delay,
+            backoff
+        );
+        delay
+    }
+
+    fn is_send(actions: &[GatewayAction], expected: fn(&ClientPayload) -> bool) -> bool {
+        matches!(actions.first(), Some(GatewayAction::Send(payload)) if expected(payload))
+    }
+
+    #[test]
+    fn authenticates_after_hello() {
+        let mut client = client();
+        let actions = event(&mut client, fixtures::hello(HEARTBEAT_INTERVAL), 0);
+
+        assert!(matches!(
+            actions.as_slice(),
+            [
+                GatewayAction::Send(ClientPayload::Authenticate(token)),
+                GatewayAction::Event(payload),
+            ] if token == "token" && matches!(**payload, ServerPayload::Hello { .. })
+        ));
+        assert_eq!(client.state(), GatewayState::Authenticating);
+    }
+
+    #[test]
+    fn resumes_after_hello() {
+        let mut client = connected();
+        client.handle_event(
+            GatewayEvent {
+                payload: ServerPayload::MessageDelete { id: 3.into() },
+                seq: Some(7),
+            },
+            1_000,
+        );
+        assert_eq!(client.seq(), 7);
+
+        client.handle_close(Some(CloseCode::ServerRestart.code()), 2_000);
+        let reconnect_at = client.next_tick().unwrap();
+        assert!(matches!(
+            client.tick(reconnect_at).as_slice(),
+            [GatewayAction::Connect]
+        ));
+        let actions = event(
+            &mut client,
+            fixtures::hello(HEARTBEAT_INTERVAL),
+            reconnect_at,
+        );
+        assert!(is_send(&actions, |payload| matches!(
+            payload,
+            ClientPayload::Resume { token, session, seq: 7 }
+                if token == "token" && *session == 1.into()
+        )));
+
+        let actions = event(&mut client, ServerPayload::Resumed, reconnect_at);
+        assert!(matches!(actions.as_slice(), [GatewayAction::Event(_)]));
+        assert_eq!(client.state(), GatewayState::Connected);
+        assert_eq!(client.seq(), 7);
+    }
+
+    #[test]
+    fn first_ping_jitter() {
+        let jitter = (Rng(SEED).next_f64() * HEARTBEAT_INTERVAL as f64) as u64;
+        assert!(jitter < HEARTBEAT_INTERVAL);
+
+        let mut client = client();
+        event(&mut client, fixtures::hello(HEARTBEAT_INTERVAL), 1_000);
+        let first_ping = 1_000 + jitter;
+        assert_eq!(client.next_tick(), Some(first_ping));
+        assert!(client.tick(first_ping - 1).is_empty());
+
+        // Every ping after the first one is a heartbeat interval apart.
+        let mut ping = first_ping;
+        for _ in 0..3 {
+            assert!(matches!(
+                client.tick(ping).as_slice(),
+                [GatewayAction::Send(ClientPayload::Ping)]
+            ));
+            assert!(event(&mut client, ServerPayload::Pong, ping + 50).is_empty());
+            ping += HEARTBEAT_INTERVAL;
+            assert_eq!(client.next_tick(), Some(ping));
+        }
+
+        // Other seeds jitter differently.
+        let mut other = GatewayClient::new("token", SEED + 1);
+        other.connect();
+        other.handle_event(fixtures::hello(HEARTBEAT_INTERVAL).into(), 1_000);
+        assert_ne!(other.next_tick(), Some(first_ping));
+    }
+
+    #[test]
+    fn missed_pong_reconnects() {
+        let mut client = connected();
+        let first_ping = client.next_tick().unwrap();
+        assert!(matches!(
+            client.tick(first_ping).as_slice(),
+            [GatewayAction::Send(ClientPayload::Ping)]
+        ));
+
+        let now = first_ping + HEARTBEAT_INTERVAL;
+        assert!(matches!(
+            client.tick(now).as_slice(),
+            [GatewayAction::Close]
+        ));
+        let delay = reconnect_delay(&client, now, BACKOFF_BASE);
+        // Closes reported by the transport afterwards don't schedule another reconnect.
+        assert!(client.handle_close(None, now).is_empty());
+        assert_eq!(client.next_tick(), Some(now + delay));
+
+        assert!(client.tick(now + delay - 1).is_empty());
+        assert!(matches!(
+            client.tick(now + delay).as_slice(),
+            [GatewayAction::Connect]
+        ));
+        assert_eq!(client.state(), GatewayState::Connecting);
+        // The session is kept so that it can be resumed.
+        assert_eq!(client.session(), Some(1.into()));
+    }
+
+    #[test]
+    fn backoff_grows_and_resets() {
+        let mut client = connected();
+        let mut now = 0;
+        for (attempt, backoff) in [1_000, 2_000, 4_000, 8_000, 16_000, 32_000, 60_000, 60_000]
+            .into_iter()
+            .enumerate()
+        {
+            assert!(client.handle_close(None, now).is_empty(), "{}", attempt);
+            now += reconnect_delay(&client, now, backoff);
+            assert!(matches!(
+                client.tick(now).as_slice(),
+                [GatewayAction::Connect]
+            ));
+        }
+
+        // Resuming resets the backoff.
+        event(&mut client, fixtures::hello(HEARTBEAT_INTERVAL), now);
+        event(&mut client, ServerPayload::Resumed, now);
+        client.handle_close(None, now);
+        now += reconnect_delay(&client, now, BACKOFF_BASE);
+        client.tick(now);
+        client.handle_close(None, now);
+        now += reconnect_delay(&client, now, 2 * BACKOFF_BASE);
+        client.tick(now);
+
+        // And so does authenticating.
+        event(&mut client, fixtures::hello(HEARTBEAT_INTERVAL), now);
+        event(
+            &mut client,
+            ServerPayload::Authenticated {
+                user: fixtures::user(2),
+                users: vec![],
+                session_id: 4.into(),
+            },
+            now,
+        );
+        client.handle_close(None, now);
+        reconnect_delay(&client, now, BACKOFF_BASE);
+    }
+
+    #[test]
+    fn invalid_session_reauthenticates() {
+        let mut client = connected();
+        client.handle_close(None, 1_000);
+        let now = client.next_tick().unwrap();
+        client.tick(now);
+        let actions = event(&mut client, fixtures::hello(HEARTBEAT_INTERVAL), now);
+        assert!(is_send(&actions, |payload| matches!(
+            payload,
+            ClientPayload::Resume { .. }
+        )));
+
+        let actions = event(&mut client, ServerPayload::InvalidSession, now);
+        assert!(matches!(
+            actions.as_slice(),
+            [
+                GatewayAction::Send(ClientPayload::Authenticate(token)),
+                GatewayAction::Event(payload),
+            ] if token == "token" && matches!(**payload, ServerPayload::InvalidSession)
+        ));
+        assert_eq!(client.session(), None);
+        assert_eq!(client.seq(), 0);
+        assert_eq!(client.state(), GatewayState::Authenticating);
+    }
+
+    #[test]
+    fn reconnect_codes_forget_the_session() {
+        let mut client = connected();
+        client.handle_close(Some(CloseCode::DecodeError.code()), 1_000);
+        assert_eq!(client.session(), None);
+
+        let now = client.next_tick().unwrap();
+        client.tick(now);
+        let actions = event(&mut client, fixtures::hello(HEARTBEAT_INTERVAL), now);
+        assert!(is_send(&actions, |payload| matches!(
+            payload,
+            ClientPayload::Authenticate(_)
+        )));
+    }
+
+    #[test]
+    fn rate_limit_delays_reconnect() {
+        let mut client = connected();
+        let actions = event(
+            &mut client,
+            ServerPayload::RateLimit { wait: 30_000 },
+            1_000,
+        );
+        assert!(matches!(actions.as_slice(), [GatewayAction::Event(_)]));
+        assert!(client.is_rate_limited(30_999));
+        assert!(!client.is_rate_limited(31_000));
+
+        client.handle_close(Some(CloseCode::RateLimited.code()), 2_000);
+        // The backoff only starts once the rate limit is over.
+        reconnect_delay(&client, 31_000, BACKOFF_BASE);
+    }
+
+    #[test]
+    fn gives_up() {
+        for code in [
+            CloseCode::AuthenticationFailed,
+            CloseCode::UnsupportedEncoding,
+        ] {
+            let mut client = connected();
+            assert!(matches!(
+                client.handle_close(Some(code.code()), 1_000).as_slice(),
+                [GatewayAction::GiveUp]
+            ));
+            assert_eq!(client.state(), GatewayState::GaveUp);
+            assert_eq!(client.next_tick(), None);
+            assert!(client.tick(u64::MAX).is_empty());
+            assert!(client.handle_close(None, 2_000).is_empty());
+            assert!(client.close().is_empty());
+        }
+    }
+
+    #[test]
+    fn close() {
+        let mut client = connected();
+
+        assert!(matches!(client.close().as_slice(), [GatewayAction::Close]));
+        assert_eq!(client.state(), GatewayState::Disconnected);
+        assert_eq!(client.session(), None);
+        assert_eq!(client.next_tick(), None);
+        assert!(client.close().is_empty());
+        assert!(client.handle_close(None, 1_000).is_empty());
+    }
+}
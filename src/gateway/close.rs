@@ -0,0 +1,137 @@
+use std::fmt;
+
+/// The close codes Pandemonium closes websocket connections with.
+///
+/// Every close code tells the client whether it should resume its session, reconnect with a new
+/// one or give up, see [`CloseCode::reconnect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CloseCode {
+    /// Something went wrong on the server's side.
+    UnknownError,
+    /// The client sent a payload that couldn't be decoded.
+    DecodeError,
+    /// The client sent a payload other than `AUTHENTICATE` or `RESUME` before authenticating.
+    NotAuthenticated,
+    /// The client's session token is invalid.
+    AuthenticationFailed,
+    /// The client sent an `AUTHENTICATE` or `RESUME` payload after having authenticated.
+    AlreadyAuthenticated,
+    /// The client didn't authenticate in time.
+    AuthenticationTimeout,
+    /// The client didn't send a `PING` payload in time.
+    HeartbeatTimeout,
+    /// The client kept sending payloads while being rate limited.
+    RateLimited,
+    /// The server is restarting.
+    ServerRestart,
+    /// The client asked for a [`GatewayEncoding`](super::GatewayEncoding) the server doesn't
+    /// support.
+    UnsupportedEncoding,
+}
+
+/// What a client should do after its connection is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReconnectPolicy {
+    /// Reconnect and resume the gateway session with a `RESUME` payload.
+    Resume,
+    /// Reconnect and start a new gateway session with an `AUTHENTICATE` payload.
+    Reconnect,
+    /// Don't reconnect, doing so would fail the same way.
+    GiveUp,
+}
+
+impl CloseCode {
+    /// All the close codes.
+    pub const ALL: [Self; 10] = [
+        Self::UnknownError,
+        Self::DecodeError,
+        Self::NotAuthenticated,
+        Self::AuthenticationFailed,
+        Self::AlreadyAuthenticated,
+        Self::AuthenticationTimeout,
+        Self::HeartbeatTimeout,
+        Self::RateLimited,
+        Self::ServerRestart,
+        Self::UnsupportedEncoding,
+    ];
+
+    /// Get the numeric close code.
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::UnknownError => 4000,
+            Self::DecodeError => 4001,
+            Self::NotAuthenticated => 4002,
+            Self::AuthenticationFailed => 4003,
+            Self::AlreadyAuthenticated => 4004,
+            Self::AuthenticationTimeout => 4005,
+            Self::HeartbeatTimeout => 4006,
+            Self::RateLimited => 4007,
+            Self::ServerRestart => 4008,
+            Self::UnsupportedEncoding => 4009,
+        }
+    }
+
+    /// Get the close code's reason, which is sent alongside it.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::UnknownError => "Unknown error",
+            Self::DecodeError => "Failed to decode payload",
+            Self::NotAuthenticated => "Not authenticated",
+            Self::AuthenticationFailed => "Authentication failed",
+            Self::AlreadyAuthenticated => "Already authenticated",
+            Self::AuthenticationTimeout => "Authentication timed out",
+            Self::HeartbeatTimeout => "Heartbeat timed out",
+            Self::RateLimited => "Rate limited",
+            Self::ServerRestart => "Server restarting",
+            Self::UnsupportedEncoding => "Unsupported encoding",
+        }
+    }
+
+    /// Get what the client should do after being disconnected with the close code.
+    pub fn reconnect(&self) -> ReconnectPolicy {
+        match self {
+            Self::UnknownError
+            | Self::HeartbeatTimeout
+            | Self::RateLimited
+            | Self::ServerRestart => ReconnectPolicy::Resume,
+            Self::DecodeError
+            | Self::NotAuthenticated
+            | Self::AlreadyAuthenticated
+            | Self::AuthenticationTimeout => ReconnectPolicy::Reconnect,
+            Self::AuthenticationFailed | Self::UnsupportedEncoding => ReconnectPolicy::GiveUp,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Get what the client should do after its connection is closed with a numeric close code.
+    ///
+    /// Connections closed with close codes that aren't [`CloseCode`]s, like abnormal closures,
+    /// are resumed.
+    pub fn from_code(code: u16) -> Self {
+        CloseCode::try_from(code).map_or(Self::Resume, |code| code.reconnect())
+    }
+}
+
+impl From<CloseCode> for u16 {
+    fn from(code: CloseCode) -> Self {
+        code.code()
+    }
+}
+
+impl TryFrom<u16> for CloseCode {
+    type Error = u16;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|close_code| close_code.code() == code)
+            .ok_or(code)
+    }
+}
+
+impl fmt::Display for CloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.code(), self.reason())
+    }
+}
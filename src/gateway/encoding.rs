@@ -0,0 +1,396 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The encodings Pandemonium payloads can be sent in.
+///
+/// Clients pick an encoding with the `encoding` query parameter of the URL they connect to,
+/// connections without one use JSON. Every encoding keeps the `op` and `d` layout of the JSON
+/// payloads, binary encodings are sent in binary websocket frames.
+///
+/// MessagePack requires the `msgpack` feature and CBOR requires the `cbor` feature.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```http
+/// wss://ws.eludris.gay/?encoding=msgpack
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GatewayEncoding {
+    /// JSON in text frames.
+    #[default]
+    Json,
+    /// MessagePack in binary frames.
+    MessagePack,
+    /// CBOR in binary frames.
+    Cbor,
+}
+
+/// The error returned when a payload can't be encoded or decoded.
+#[derive(Debug)]
+pub enum EncodingError {
+    /// The encoding isn't supported by this build.
+    Unsupported(GatewayEncoding),
+    /// The payload couldn't be encoded.
+    Encode(Box<dyn Error + Send + Sync>),
+    /// The payload couldn't be decoded.
+    Decode(Box<dyn Error + Send + Sync>),
+}
+
+impl GatewayEncoding {
+    /// The name of the query parameter holding the encoding.
+    pub const QUERY_PARAMETER: &'static str = "encoding";
+
+    /// Get the encoding's name, as used in the connect URL.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::MessagePack => "msgpack",
+            Self::Cbor => "cbor",
+        }
+    }
+
+    /// Whether this build can encode and decode payloads in the encoding.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Self::Json => true,
+            Self::MessagePack => cfg!(feature = "msgpack"),
+            Self::Cbor => cfg!(feature = "cbor"),
+        }
+    }
+
+    /// Whether payloads in the encoding are sent in binary websocket frames.
+    pub fn is_binary(&self) -> bool {
+        *self != Self::Json
+    }
+
+    /// Get the encoding a client asked for from the URL it connected to.
+    ///
+    /// Returns `None` if the encoding is empty, unknown or unsupported, in which case the connection
+    /// should be closed with [`CloseCode::UnsupportedEncoding`](super::CloseCode).
+    pub fn from_url(url: &str) -> Option<Self> {
+        let query = match url.split_once('?') {
+            Some((_, query)) => query.split('#').next().unwrap_or_default(),
+            None => return Some(Self::Json),
+        };
+        match query
+            .split('&')
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .find(|(key, _)| *key == Self::QUERY_PARAMETER)
+        {
+            Some((_, value)) => value.parse().ok().filter(Self::is_supported),
+            None => Some(Self::Json),
+        }
+    }
+
+    /// Add the encoding to the URL a client connects to.
+    pub fn to_url(&self, url: &str) -> String {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!(
+            "{}{}{}={}",
+            url,
+            separator,
+            Self::QUERY_PARAMETER,
+            self.as_str()
+        )
+    }
+
+    /// Encode a payload.
+    pub fn encode<T: Serialize>(&self, payload: &T) -> Result<Vec<u8>, EncodingError> {
+        match self {
+            Self::Json => {
+                serde_json::to_vec(payload).map_err(|err| EncodingError::Encode(err.into()))
+            }
+            // Structs have to be encoded as maps to keep the `op` and `d` keys.
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => {
+                rmp_serde::to_vec_named(payload).map_err(|err| EncodingError::Encode(err.into()))
+            }
+            #[cfg(feature = "cbor")]
+            Self::Cbor => {
+                let mut buffer = Vec::new();
+                ciborium::into_writer(payload, &mut buffer)
+                    .map_err(|err| EncodingError::Encode(err.into()))?;
+                Ok(buffer)
+            }
+            #[allow(unreachable_patterns)]
+            encoding => Err(EncodingError::Unsupported(*encoding)),
+        }
+    }
+
+    /// Decode a payload.
+    pub fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, EncodingError> {
+        match self {
+            Self::Json => {
+                serde_json::from_slice(data).map_err(|err| EncodingError::Decode(err.into()))
+            }
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => {
+                rmp_serde::from_slice(data).map_err(|err| EncodingError::Decode(err.into()))
+            }
+            #[cfg(feature = "cbor")]
+            Self::Cbor => {
+                ciborium::from_reader(data).map_err(|err| EncodingError::Decode(err.into()))
+            }
+            #[allow(unreachable_patterns)]
+            encoding => Err(EncodingError::Unsupported(*encoding)),
+        }
+    }
+}
+
+impl FromStr for GatewayEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::MessagePack),
+            "cbor" => Ok(Self::Cbor),
+            _ => Err(format!("Unknown encoding {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for GatewayEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unsupported(encoding) => write!(f, "Unsupported encoding {}", encoding),
+            Self::Encode(err) => write!(f, "Failed to encode payload: {}", err),
+            Self::Decode(err) => write!(f, "Failed to decode payload: {}", err),
+        }
+    }
+}
+
+impl Error for EncodingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Unsupported(_) => None,
+            Self::Encode(err) | Self::Decode(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures, ClientPayload, GatewayEvent, Message, MessageCreate, MessageDisguise,
+        MessageEdit, ServerPayload, Status, StatusType, User,
+    };
+
+    const ENCODINGS: [GatewayEncoding; 3] = [
+        GatewayEncoding::Json,
+        GatewayEncoding::MessagePack,
+        GatewayEncoding::Cbor,
+    ];
+
+    /// A user with some of the optional fields set, so that they're round tripped too.
+    fn user(id: u64) -> User {
+        User {
+            display_name: Some("Nicolas".to_string()),
+            social_credit: -69420,
+            status: Status {
+                status_type: StatusType::Busy,
+                text: Some("ayúdame por favor".to_string()),
+            },
+            avatar: Some(2.into()),
+            email: None,
+            ..fixtures::user(id)
+        }
+    }
+
+    fn server_payloads() -> Vec<ServerPayload> {
+        vec![
+            ServerPayload::Pong,
+            ServerPayload::RateLimit { wait: 1010 },
+            fixtures::hello(45_000),
+            ServerPayload::Authenticated {
+                user: user(1),
+                users: vec![user(3), user(4)],
+                session_id: 5.into(),
+            },
+            ServerPayload::Resumed,
+            ServerPayload::InvalidSession,
+            ServerPayload::UserUpdate(user(1)),
+            ServerPayload::PresenceUpdate {
+                user_id: 1.into(),
+                status: Status {
+                    status_type: StatusType::Online,
+                    text: None,
+                },
+            },
+            ServerPayload::MessageCreate(Message {
+                id: 6.into(),
+                author: user(1),
+                message: MessageCreate {
+                    content: "Hello, World!".to_string(),
+                    disguise: Some(MessageDisguise {
+                        name: Some("Jeff".to_string()),
+                        avatar: None,
+                    }),
+                },
+            }),
+            ServerPayload::MessageUpdate {
+                id: 6.into(),
+                editor_id: 1.into(),
+                edited_at: 1_700_000_000_000,
+                changes: MessageEdit {
+                    content: Some("Hello, Eludris!".to_string()),
+                },
+            },
+            ServerPayload::MessageDelete { id: 6.into() },
+            ServerPayload::TypingStart {
+                user_id: 1.into(),
+                timestamp: 1_700_000_000_000,
+            },
+        ]
+    }
+
+    fn client_payloads() -> Vec<ClientPayload> {
+        vec![
+            ClientPayload::Ping,
+            ClientPayload::Authenticate("token".to_string()),
+            ClientPayload::Resume {
+                token: "token".to_string(),
+                session: 5.into(),
+                seq: 42,
+            },
+            ClientPayload::Typing,
+        ]
+    }
+
+    /// Round trip a value through every supported encoding, checking that it comes back the same
+    /// and keeps the layout of its JSON form.
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) {
+        let expected = serde_json::to_value(value).unwrap();
+        for encoding in ENCODINGS.into_iter().filter(GatewayEncoding::is_supported) {
+            let data = encoding.encode(value).unwrap();
+            let decoded: T = encoding.decode(&data).unwrap();
+            assert_eq!(
+                serde_json::to_value(decoded).unwrap(),
+                expected,
+                "{}",
+                encoding
+            );
+            let layout: serde_json::Value = encoding.decode(&data).unwrap();
+            assert_eq!(layout, expected, "{}", encoding);
+        }
+    }
+
+    #[test]
+    fn server_payloads_round_trip() {
+        for payload in server_payloads() {
+            round_trip(&payload);
+        }
+    }
+
+    #[test]
+    fn client_payloads_round_trip() {
+        for payload in client_payloads() {
+            round_trip(&payload);
+        }
+    }
+
+    #[test]
+    fn gateway_events_round_trip() {
+        for (seq, payload) in server_payloads().into_iter().enumerate() {
+            let seq = payload.is_dispatch().then_some(seq as u64 + 1);
+            round_trip(&GatewayEvent { payload, seq });
+        }
+    }
+
+    #[test]
+    fn unsupported_encodings() {
+        for encoding in ENCODINGS {
+            if encoding.is_supported() {
+                continue;
+            }
+            assert!(matches!(
+                encoding.encode(&ClientPayload::Ping),
+                Err(EncodingError::Unsupported(unsupported)) if unsupported == encoding
+            ));
+            assert!(matches!(
+                encoding.decode::<ClientPayload>(&[]),
+                Err(EncodingError::Unsupported(unsupported)) if unsupported == encoding
+            ));
+            assert_eq!(
+                GatewayEncoding::from_url(&encoding.to_url("wss://example.com")),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn decode_error() {
+        for encoding in ENCODINGS.into_iter().filter(GatewayEncoding::is_supported) {
+            assert!(matches!(
+                encoding.decode::<ClientPayload>(b"\xff"),
+                Err(EncodingError::Decode(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn from_url() {
+        let json = Some(GatewayEncoding::Json);
+        assert_eq!(GatewayEncoding::from_url("wss://example.com"), json);
+        assert_eq!(GatewayEncoding::from_url("wss://example.com/?"), json);
+        assert_eq!(GatewayEncoding::from_url("wss://example.com/?v=1"), json);
+        assert_eq!(
+            GatewayEncoding::from_url("wss://example.com/#encoding=xml"),
+            json
+        );
+        assert_eq!(
+            GatewayEncoding::from_url("wss://example.com/?v=1&encoding=json#cbor"),
+            json
+        );
+
+        assert_eq!(
+            GatewayEncoding::from_url("wss://example.com/?encoding=xml"),
+            None
+        );
+        assert_eq!(
+            GatewayEncoding::from_url("wss://example.com/?encoding=JSON"),
+            None
+        );
+        assert_eq!(
+            GatewayEncoding::from_url("wss://example.com/?encoding="),
+            None
+        );
+        assert_eq!(
+            GatewayEncoding::from_url("wss://example.com/?encoding"),
+            None
+        );
+        assert_eq!(
+            GatewayEncoding::from_url("wss://example.com/?encoding=&v=1"),
+            None
+        );
+    }
+
+    #[test]
+    fn to_url() {
+        assert_eq!(
+            GatewayEncoding::Json.to_url("wss://example.com"),
+            "wss://example.com?encoding=json"
+        );
+        assert_eq!(
+            GatewayEncoding::MessagePack.to_url("wss://example.com/?v=1"),
+            "wss://example.com/?v=1&encoding=msgpack"
+        );
+        for encoding in ENCODINGS.into_iter().filter(GatewayEncoding::is_supported) {
+            assert_eq!(
+                GatewayEncoding::from_url(&encoding.to_url("wss://example.com")),
+                Some(encoding)
+            );
+            assert_eq!(encoding.as_str().parse(), Ok(encoding));
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::{CloseCode, ServerPayload, TYPING_THROTTLE};
+use crate::conf::RateLimitConf;
+
+/// A per-connection rate limiter for the payloads a client sends to Pandemonium.
+///
+/// This is driven by the instance's `PandemoniumConf.rate_limit`. Once a client goes over it
+/// they get a [`ServerPayload::RateLimit`] and every payload they send before the window resets
+/// is a violation, going over `max_violations` gets them disconnected.
+#[derive(Debug, Clone)]
+pub struct GatewayRateLimiter {
+    reset_after: u64,
+    limit: u32,
+    max_violations: u32,
+    reset_at: u64,
+    count: u32,
+    violations: u32,
+    /// When the last typing payload that was counted was sent.
+    last_typing: Option<u64>,
+}
+
+/// The outcome of checking a client payload against a [`GatewayRateLimiter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GatewayRateLimitDecision {
+    /// The payload is allowed and should be handled.
+    Allowed,
+    /// The client just got rate limited, the payload should be dropped and the client sent a
+    /// [`ServerPayload::RateLimit`].
+    RateLimited {
+        /// The amount of milliseconds until the rate limit ends.
+        wait: u64,
+    },
+    /// The payload was sent too soon after the previous one of its kind and should be dropped.
+    ///
+    /// This doesn't count towards the rate limit.
+    Throttled,
+    /// The client sent a payload while rate limited, the payload should be dropped.
+    ///
+    /// This counts as a violation.
+    Ignored,
+    /// The client kept sending payloads while rate limited and should be disconnected with the
+    /// close code.
+    Disconnect(CloseCode),
+}
+
+impl GatewayRateLimiter {
+    /// The default amount of payloads a client can send while rate limited before getting
+    /// disconnected.
+    ///
+    /// This leaves some leeway for payloads that were already sent before the client received
+    /// the `RATE_LIMIT` payload.
+    pub const DEFAULT_MAX_VIOLATIONS: u32 = 3;
+
+    /// Create a new [`GatewayRateLimiter`] for a connection.
+    pub fn new(conf: &RateLimitConf) -> Self {
+        Self {
+            reset_after: conf.reset_after.as_millis() as u64,
+            limit: conf.limit,
+            max_violations: Self::DEFAULT_MAX_VIOLATIONS,
+            reset_at: 0,
+            count: 0,
+            violations: 0,
+            last_typing: None,
+        }
+    }
+
+    /// Set the amount of payloads a client can send while rate limited before getting
+    /// disconnected.
+    pub fn with_max_violations(mut self, max_violations: u32) -> Self {
+        self.max_violations = max_violations;
+        self
+    }
+
+    /// Check a payload the client sent at `now`, counting it if it's allowed.
+    pub fn check(&mut self, now: u64) -> GatewayRateLimitDecision {
+        if self.reset_at <= now {
+            self.reset_at = now + self.reset_after;
+            self.count = 0;
+            self.violations = 0;
+        }
+
+        if self.count < self.limit {
+            self.count += 1;
+            GatewayRateLimitDecision::Allowed
+        } else if self.count == self.limit {
+            // Push the count over the limit so that later payloads count as violations.
+            self.count += 1;
+            GatewayRateLimitDecision::RateLimited {
+                wait: self.reset_at - now,
+            }
+        } else if self.violations < self.max_violations {
+            self.violations += 1;
+            GatewayRateLimitDecision::Ignored
+        } else {
+            GatewayRateLimitDecision::Disconnect(CloseCode::RateLimited)
+        }
+    }
+
+    /// Check a `TYPING` payload the client sent at `now`.
+    ///
+    /// Only one typing payload per [`TYPING_THROTTLE`] counts towards the rate limit, the rest are
+    /// throttled so that typing can't use up the client's whole budget.
+    pub fn check_typing(&mut self, now: u64) -> GatewayRateLimitDecision {
+        if self
+            .last_typing
+            .is_some_and(|last_typing| now < last_typing + TYPING_THROTTLE)
+        {
+            return GatewayRateLimitDecision::Throttled;
+        }
+        let decision = self.check(now);
+        if decision.is_allowed() {
+            self.last_typing = Some(now);
+        }
+        decision
+    }
+
+    /// Whether the client is rate limited at `now`.
+    pub fn is_limited(&self, now: u64) -> bool {
+        self.reset_at > now && self.count > self.limit
+    }
+
+    /// Get the amount of milliseconds until the rate limit ends at `now`, if the client is rate
+    /// limited.
+    pub fn wait(&self, now: u64) -> Option<u64> {
+        self.is_limited(now).then(|| self.reset_at - now)
+    }
+
+    /// Get the amount of violations in the current window.
+    pub fn violations(&self) -> u32 {
+        self.violations
+    }
+}
+
+impl GatewayRateLimitDecision {
+    /// Whether the payload is allowed.
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allowed)
+    }
+
+    /// Get the payload to send to the client, if any.
+    pub fn payload(&self) -> Option<ServerPayload> {
+        match self {
+            Self::RateLimited { wait } => Some(ServerPayload::RateLimit { wait: *wait }),
+            _ => None,
+        }
+    }
+}
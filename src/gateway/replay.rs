@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+
+use super::{GatewayEvent, ServerPayload};
+
+/// A buffer of the events dispatched in a gateway session, used to resume it.
+///
+/// The buffer hands out sequence numbers to dispatched events and keeps the last `capacity` of
+/// them around. A client resuming its session gets every event after the last one it received,
+/// unless some of them have already been evicted.
+#[derive(Debug, Clone)]
+pub struct ReplayBuffer {
+    capacity: usize,
+    events: VecDeque<GatewayEvent>,
+    seq: u64,
+}
+
+impl ReplayBuffer {
+    /// The default amount of events a [`ReplayBuffer`] keeps.
+    pub const DEFAULT_CAPACITY: usize = 1000;
+
+    /// Create a new [`ReplayBuffer`] that keeps the last `capacity` events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: VecDeque::new(),
+            seq: 0,
+        }
+    }
+
+    /// Get the sequence number of the last dispatched event, 0 if there's none.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Get the amount of events in the buffer.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether the buffer has no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Turn a payload into the [`GatewayEvent`] to send to the client.
+    ///
+    /// Dispatched events get the next sequence number and are buffered, evicting the oldest
+    /// event if the buffer is full. Other payloads are passed through without one.
+    pub fn push(&mut self, payload: ServerPayload) -> GatewayEvent {
+        if !payload.is_dispatch() {
+            return payload.into();
+        }
+
+        self.seq += 1;
+        let event = GatewayEvent {
+            payload,
+            seq: Some(self.seq),
+        };
+        if self.capacity > 0 {
+            if self.events.len() == self.capacity {
+                self.events.pop_front();
+            }
+            self.events.push_back(event.clone());
+        }
+        event
+    }
+
+    /// Get the events dispatched after `seq`, in order.
+    ///
+    /// Returns `None` if some of them have been evicted or `seq` is ahead of the session, in
+    /// which case the client has to authenticate again.
+    pub fn replay(&self, seq: u64) -> Option<Vec<GatewayEvent>> {
+        if seq > self.seq {
+            return None;
+        }
+        // The first sequence number that's still available.
+        let first = self.seq + 1 - self.events.len() as u64;
+        if seq + 1 < first {
+            return None;
+        }
+        Some(
+            self.events
+                .iter()
+                .skip((seq + 1 - first) as usize)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Get the payloads to send to a client resuming its session from `seq`.
+    ///
+    /// This is a `RESUMED` payload followed by the missed events, or a single `INVALID_SESSION`
+    /// payload if the session can't be resumed.
+    pub fn resume(&self, seq: u64) -> Vec<GatewayEvent> {
+        match self.replay(seq) {
+            Some(events) => {
+                let mut payloads = Vec::with_capacity(events.len() + 1);
+                payloads.push(ServerPayload::Resumed.into());
+                payloads.extend(events);
+                payloads
+            }
+            None => vec![ServerPayload::InvalidSession.into()],
+        }
+    }
+}
+
+impl Default for ReplayBuffer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
@@ -0,0 +1,519 @@
+use super::{
+    ClientPayload, CloseCode, GatewayRateLimitDecision, GatewayRateLimiter, ServerPayload,
+};
+use crate::{conf::Conf, Id};
+
+/// The amount of milliseconds a client has to authenticate after connecting.
+pub const AUTH_TIMEOUT: u64 = 10_000;
+
+/// The amount of milliseconds a client's ping can be late by before it's disconnected.
+pub const HEARTBEAT_GRACE: u64 = 10_000;
+
+/// The state of a [`GatewayConnection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GatewayConnectionState {
+    /// The client has yet to send an `AUTHENTICATE` or `RESUME` payload.
+    AwaitingAuth,
+    /// The client sent an `AUTHENTICATE` or `RESUME` payload which the server is checking.
+    Authenticating,
+    /// The client is authenticated.
+    Authenticated,
+    /// The connection is closed.
+    Closed,
+}
+
+/// Something a [`GatewayConnection`]'s server has to do.
+#[derive(Debug, Clone)]
+pub enum GatewayConnectionAction {
+    /// Send a payload to the client.
+    Send(Box<ServerPayload>),
+    /// Check the client's session token, then call [`GatewayConnection::authenticated`] or
+    /// [`GatewayConnection::authentication_failed`].
+    Authenticate(String),
+    /// Check the client's session token and resume its gateway session, then call
+    /// [`GatewayConnection::authenticated`], [`GatewayConnection::authentication_failed`] or
+    /// [`GatewayConnection::resume_failed`].
+    Resume {
+        /// The session token the client authenticated with.
+        token: String,
+        /// The ID of the gateway session to resume.
+        session: Id,
+        /// The sequence number of the last event the client received.
+        seq: u64,
+    },
+    /// Handle a payload from an authenticated client.
+    Handle(ClientPayload),
+    /// Close the connection with the close code.
+    Close(CloseCode),
+}
+
+/// The server side of a gateway connection.
+///
+/// The connection implements the Pandemonium protocol: it sends the `HELLO` payload, requires
+/// the client to authenticate within [`AUTH_TIMEOUT`], rejects every other payload before that,
+/// answers pings and disconnects clients that miss them. Payloads from authenticated clients go
+/// through a [`GatewayRateLimiter`]. Pings are required by the protocol so they're answered
+/// without it, but only once every half `heartbeat_interval`, earlier pings go through the rate
+/// limiter instead, even before the client authenticates.
+///
+/// Servers feed it the payloads they receive and timer ticks and carry out the
+/// [`GatewayConnectionAction`]s it returns.
+#[derive(Debug, Clone)]
+pub struct GatewayConnection {
+    state: GatewayConnectionState,
+    heartbeat_interval: u64,
+    auth_deadline: u64,
+    ping_deadline: u64,
+    /// When the last ping that was answered was sent.
+    last_ping: Option<u64>,
+    rate_limiter: GatewayRateLimiter,
+}
+
+impl GatewayConnection {
+    /// Open a new connection at `now`, returning it and the actions to start it with.
+    pub fn open(
+        conf: &Conf,
+        heartbeat_interval: u64,
+        now: u64,
+    ) -> (Self, Vec<GatewayConnectionAction>) {
+        let connection = Self {
+            state: GatewayConnectionState::AwaitingAuth,
+            heartbeat_interval,
+            auth_deadline: now + AUTH_TIMEOUT,
+            ping_deadline: now + heartbeat_interval + HEARTBEAT_GRACE,
+            last_ping: None,
+            rate_limiter: GatewayRateLimiter::new(&conf.pandemonium.rate_limit),
+        };
+        let hello = ServerPayload::hello(conf, heartbeat_interval);
+        (connection, vec![send(hello)])
+    }
+
+    /// Use a custom [`GatewayRateLimiter`] for the connection.
+    pub fn with_rate_limiter(mut self, rate_limiter: GatewayRateLimiter) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Get the state of the connection.
+    pub fn state(&self) -> GatewayConnectionState {
+        self.state
+    }
+
+    /// Handle a payload the client sent at `now`.
+    pub fn handle_payload(
+        &mut self,
+        payload: ClientPayload,
+        now: u64,
+    ) -> Vec<GatewayConnectionAction> {
+        if self.state == GatewayConnectionState::Closed {
+            return vec![];
+        }
+
+        match payload {
+            ClientPayload::Ping => {
+                if self
+                    .last_ping
+                    .is_some_and(|last_ping| now < last_ping + self.heartbeat_interval / 2)
+                {
+                    let decision = self.rate_limiter.check(now);
+                    return self.handle_decision(decision, vec![]);
+                }
+                self.last_ping = Some(now);
+                self.ping_deadline = now + self.heartbeat_interval + HEARTBEAT_GRACE;
+                vec![send(ServerPayload::Pong)]
+            }
+            ClientPayload::Authenticate(token) => match self.state {
+                GatewayConnectionState::AwaitingAuth => {
+                    self.state = GatewayConnectionState::Authenticating;
+                    vec![GatewayConnectionAction::Authenticate(token)]
+                }
+                _ => self.close(CloseCode::AlreadyAuthenticated),
+            },
+            ClientPayload::Resume {
+                token,
+                session,
+                seq,
+            } => match self.state {
+                GatewayConnectionState::AwaitingAuth => {
+                    self.state = GatewayConnectionState::Authenticating;
+                    vec![GatewayConnectionAction::Resume {
+                        token,
+                        session,
+                        seq,
+                    }]
+                }
+                _ => self.close(CloseCode::AlreadyAuthenticated),
+            },
+            _ if self.state != GatewayConnectionState::Authenticated => {
+                self.close(CloseCode::NotAuthenticated)
+            }
+            payload => {
+                let decision = match payload {
+                    ClientPayload::Typing => self.rate_limiter.check_typing(now),
+                    _ => self.rate_limiter.check(now),
+                };
+                self.handle_decision(decision, vec![GatewayConnectionAction::Handle(payload)])
+            }
+        }
+    }
+
+    /// Handle a payload from the client that couldn't be decoded.
+    pub fn handle_decode_error(&mut self) -> Vec<GatewayConnectionAction> {
+        self.close(CloseCode::DecodeError)
+    }
+
+    /// Mark the client as authenticated after it was successfully authenticated or resumed.
+    ///
+    /// The server is still responsible for sending the `AUTHENTICATED` or `RESUMED` payload.
+    pub fn authenticated(&mut self) {
+        if self.state == GatewayConnectionState::Authenticating {
+            self.state = GatewayConnectionState::Authenticated;
+        }
+    }
+
+    /// Close the connection after the client's session token turned out to be invalid.
+    pub fn authentication_failed(&mut self) -> Vec<GatewayConnectionAction> {
+        self.close(CloseCode::AuthenticationFailed)
+    }
+
+    /// Tell the client to authenticate again at `now` after its gateway session turned out not
+    /// to be resumable.
+    pub fn resume_failed(&mut self, now: u64) -> Vec<GatewayConnectionAction> {
+        if self.state != GatewayConnectionState::Authenticating {
+            return vec![];
+        }
+        self.state = GatewayConnectionState::AwaitingAuth;
+        self.auth_deadline = now + AUTH_TIMEOUT;
+        vec![send(ServerPayload::InvalidSession)]
+    }
+
+    /// Advance the connection's timers to `now`.
+    pub fn tick(&mut self, now: u64) -> Vec<GatewayConnectionAction> {
+        match self.state {
+            GatewayConnectionState::Closed => vec![],
+            GatewayConnectionState::AwaitingAuth | GatewayConnectionState::Authenticating
+                if self.auth_deadline <= now =>
+            {
+                self.close(CloseCode::AuthenticationTimeout)
+            }
+            _ if self.ping_deadline <= now => self.close(CloseCode::HeartbeatTimeout),
+            _ => vec![],
+        }
+    }
+
+    /// Get when [`GatewayConnection::tick`] should be called next, if at all.
+    pub fn next_tick(&self) -> Option<u64> {
+        match self.state {
+            GatewayConnectionState::Closed => None,
+            GatewayConnectionState::Authenticated => Some(self.ping_deadline),
+            _ => Some(self.auth_deadline.min(self.ping_deadline)),
+        }
+    }
+
+    /// Close the connection with a close code, like [`CloseCode::ServerRestart`].
+    pub fn close(&mut self, code: CloseCode) -> Vec<GatewayConnectionAction> {
+        if self.state == GatewayConnectionState::Closed {
+            return vec![];
+        }
+        self.state = GatewayConnectionState::Closed;
+        vec![GatewayConnectionAction::Close(code)]
+    }
+
+    /// Carry out a rate limit decision, returning `allowed` if the payload is allowed.
+    fn handle_decision(
+        &mut self,
+        decision: GatewayRateLimitDecision,
+        allowed: Vec<GatewayConnectionAction>,
+    ) -> Vec<GatewayConnectionAction> {
+        match decision {
+            GatewayRateLimitDecision::Allowed => allowed,
+            GatewayRateLimitDecision::RateLimited { wait } => {
+                vec![send(ServerPayload::RateLimit { wait })]
+            }
+            GatewayRateLimitDecision::Throttled | GatewayRateLimitDecision::Ignored => vec![],
+            GatewayRateLimitDecision::Disconnect(code) => self.close(code),
+        }
+    }
+}
+
+fn send(payload: ServerPayload) -> GatewayConnectionAction {
+    GatewayConnectionAction::Send(Box::new(payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{conf::RateLimitConf, fixtures};
+
+    const HEARTBEAT_INTERVAL: u64 = 45_000;
+
+    /// Open a connection at 0 which allows 2 payloads a minute and a single violation.
+    fn open() -> GatewayConnection {
+        let (connection, actions) =
+            GatewayConnection::open(&fixtures::conf(), HEARTBEAT_INTERVAL, 0);
+        assert!(matches!(
+            actions.as_slice(),
+            [GatewayConnectionAction::Send(payload)] if matches!(**payload, ServerPayload::Hello { .. })
+        ));
+        connection.with_rate_limiter(
+            GatewayRateLimiter::new(&RateLimitConf {
+                reset_after: Duration::from_secs(60),
+                limit: 2,
+            })
+            .with_max_violations(1),
+        )
+    }
+
+    /// Open a connection and authenticate it at 0.
+    fn authenticate() -> GatewayConnection {
+        let mut connection = open();
+        let actions = connection.handle_payload(ClientPayload::Authenticate("token".into()), 0);
+        assert!(matches!(
+            actions.as_slice(),
+            [GatewayConnectionAction::Authenticate(token)] if token == "token"
+        ));
+        connection.authenticated();
+        assert_eq!(connection.state(), GatewayConnectionState::Authenticated);
+        connection
+    }
+
+    fn is_pong(actions: &[GatewayConnectionAction]) -> bool {
+        matches!(
+            actions,
+            [GatewayConnectionAction::Send(payload)] if matches!(**payload, ServerPayload::Pong)
+        )
+    }
+
+    fn is_rate_limit(actions: &[GatewayConnectionAction], expected: u64) -> bool {
+        matches!(
+            actions,
+            [GatewayConnectionAction::Send(payload)]
+                if matches!(**payload, ServerPayload::RateLimit { wait } if wait == expected)
+        )
+    }
+
+    fn is_close(actions: &[GatewayConnectionAction], expected: CloseCode) -> bool {
+        matches!(actions, [GatewayConnectionAction::Close(code)] if *code == expected)
+    }
+
+    #[test]
+    fn pings_are_answered_once_per_half_interval() {
+        let mut connection = authenticate();
+
+        assert!(is_pong(
+            &connection.handle_payload(ClientPayload::Ping, 100)
+        ));
+        assert!(connection
+            .handle_payload(ClientPayload::Ping, 200)
+            .is_empty());
+        // Early pings don't push the heartbeat deadline back.
+        assert_eq!(
+            connection.next_tick(),
+            Some(100 + HEARTBEAT_INTERVAL + HEARTBEAT_GRACE)
+        );
+
+        let now = 100 + HEARTBEAT_INTERVAL / 2;
+        assert!(is_pong(
+            &connection.handle_payload(ClientPayload::Ping, now)
+        ));
+    }
+
+    #[test]
+    fn ping_flood_disconnects() {
+        let mut connection = open();
+
+        assert!(is_pong(&connection.handle_payload(ClientPayload::Ping, 0)));
+        assert!(connection.handle_payload(ClientPayload::Ping, 1).is_empty());
+        assert!(connection.handle_payload(ClientPayload::Ping, 2).is_empty());
+        assert!(is_rate_limit(
+            &connection.handle_payload(ClientPayload::Ping, 3),
+            59_998
+        ));
+        assert!(connection.handle_payload(ClientPayload::Ping, 4).is_empty());
+        assert!(is_close(
+            &connection.handle_payload(ClientPayload::Ping, 5),
+            CloseCode::RateLimited
+        ));
+        assert_eq!(connection.state(), GatewayConnectionState::Closed);
+    }
+
+    fn resume(seq: u64) -> ClientPayload {
+        ClientPayload::Resume {
+            token: "token".into(),
+            session: 1.into(),
+            seq,
+        }
+    }
+
+    #[test]
+    fn payload_before_auth() {
+        let mut connection = open();
+
+        assert!(is_close(
+            &connection.handle_payload(ClientPayload::Typing, 0),
+            CloseCode::NotAuthenticated
+        ));
+        assert_eq!(connection.state(), GatewayConnectionState::Closed);
+        // Nothing happens after the connection is closed.
+        assert!(connection.handle_payload(ClientPayload::Ping, 0).is_empty());
+        assert!(connection.tick(AUTH_TIMEOUT).is_empty());
+        assert_eq!(connection.next_tick(), None);
+    }
+
+    #[test]
+    fn payload_while_authenticating() {
+        let mut connection = open();
+        connection.handle_payload(ClientPayload::Authenticate("token".into()), 0);
+
+        assert!(is_close(
+            &connection.handle_payload(ClientPayload::Typing, 0),
+            CloseCode::NotAuthenticated
+        ));
+    }
+
+    #[test]
+    fn double_authenticate() {
+        let mut connection = open();
+        connection.handle_payload(ClientPayload::Authenticate("token".into()), 0);
+
+        assert!(is_close(
+            &connection.handle_payload(ClientPayload::Authenticate("token".into()), 0),
+            CloseCode::AlreadyAuthenticated
+        ));
+
+        let mut connection = authenticate();
+        assert!(is_close(
+            &connection.handle_payload(ClientPayload::Authenticate("token".into()), 0),
+            CloseCode::AlreadyAuthenticated
+        ));
+    }
+
+    #[test]
+    fn resume_after_auth() {
+        let mut connection = authenticate();
+
+        assert!(is_close(
+            &connection.handle_payload(resume(1), 0),
+            CloseCode::AlreadyAuthenticated
+        ));
+    }
+
+    #[test]
+    fn auth_timeout() {
+        let mut connection = open();
+        assert_eq!(connection.next_tick(), Some(AUTH_TIMEOUT));
+        connection.handle_payload(ClientPayload::Authenticate("token".into()), 0);
+        assert_eq!(connection.state(), GatewayConnectionState::Authenticating);
+
+        // The server taking too long to check the token doesn't extend the deadline.
+        assert!(connection.tick(AUTH_TIMEOUT - 1).is_empty());
+        assert!(is_close(
+            &connection.tick(AUTH_TIMEOUT),
+            CloseCode::AuthenticationTimeout
+        ));
+        // The server finishing late doesn't bring the connection back.
+        connection.authenticated();
+        assert_eq!(connection.state(), GatewayConnectionState::Closed);
+    }
+
+    #[test]
+    fn authentication_failed() {
+        let mut connection = open();
+        connection.handle_payload(ClientPayload::Authenticate("token".into()), 0);
+
+        assert!(is_close(
+            &connection.authentication_failed(),
+            CloseCode::AuthenticationFailed
+        ));
+    }
+
+    #[test]
+    fn heartbeat_timeout() {
+        let mut connection = authenticate();
+        assert!(is_pong(
+            &connection.handle_payload(ClientPayload::Ping, 1_000)
+        ));
+        let deadline = 1_000 + HEARTBEAT_INTERVAL + HEARTBEAT_GRACE;
+        assert_eq!(connection.next_tick(), Some(deadline));
+
+        assert!(connection.tick(deadline - 1).is_empty());
+        assert!(is_close(
+            &connection.tick(deadline),
+            CloseCode::HeartbeatTimeout
+        ));
+    }
+
+    #[test]
+    fn resume_failed_reauthenticates() {
+        let mut connection = open();
+        let actions = connection.handle_payload(resume(5), 1_000);
+        assert!(matches!(
+            actions.as_slice(),
+            [GatewayConnectionAction::Resume { token, session, seq: 5 }]
+                if token == "token" && *session == 1.into()
+        ));
+
+        let actions = connection.resume_failed(2_000);
+        assert!(matches!(
+            actions.as_slice(),
+            [GatewayConnectionAction::Send(payload)]
+                if matches!(**payload, ServerPayload::InvalidSession)
+        ));
+        assert_eq!(connection.state(), GatewayConnectionState::AwaitingAuth);
+        // The client gets a fresh auth timeout to authenticate again.
+        assert_eq!(connection.next_tick(), Some(2_000 + AUTH_TIMEOUT));
+        assert!(connection.tick(AUTH_TIMEOUT).is_empty());
+
+        let actions = connection.handle_payload(ClientPayload::Authenticate("token".into()), 3_000);
+        assert!(matches!(
+            actions.as_slice(),
+            [GatewayConnectionAction::Authenticate(token)] if token == "token"
+        ));
+        connection.authenticated();
+        assert_eq!(connection.state(), GatewayConnectionState::Authenticated);
+        // It's not possible to fail a resume that isn't in progress.
+        assert!(connection.resume_failed(4_000).is_empty());
+    }
+
+    #[test]
+    fn rate_limit_escalation() {
+        let mut connection = authenticate();
+
+        for now in [0, 5_000] {
+            assert!(matches!(
+                connection
+                    .handle_payload(ClientPayload::Typing, now)
+                    .as_slice(),
+                [GatewayConnectionAction::Handle(ClientPayload::Typing)]
+            ));
+        }
+        // Throttled typing payloads don't count towards the rate limit.
+        assert!(connection
+            .handle_payload(ClientPayload::Typing, 6_000)
+            .is_empty());
+        assert!(is_rate_limit(
+            &connection.handle_payload(ClientPayload::Typing, 10_000),
+            50_000
+        ));
+        assert!(connection
+            .handle_payload(ClientPayload::Typing, 15_000)
+            .is_empty());
+        assert!(is_close(
+            &connection.handle_payload(ClientPayload::Typing, 20_000),
+            CloseCode::RateLimited
+        ));
+    }
+
+    #[test]
+    fn decode_error() {
+        let mut connection = authenticate();
+
+        assert!(is_close(
+            &connection.handle_decode_error(),
+            CloseCode::DecodeError
+        ));
+        assert!(connection.close(CloseCode::ServerRestart).is_empty());
+    }
+}
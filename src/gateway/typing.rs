@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use super::ServerPayload;
+use crate::Id;
+
+/// The minimum amount of milliseconds between two `TYPING_START` payloads for the same user.
+pub const TYPING_THROTTLE: u64 = 5_000;
+
+/// The amount of milliseconds after which a `TYPING_START` payload expires.
+pub const TYPING_TIMEOUT: u64 = 10_000;
+
+/// A server-side throttle for typing indicators.
+///
+/// This is shared between every connection of the instance so that users with multiple
+/// connections don't get broadcast more than once per [`TYPING_THROTTLE`].
+#[cfg(feature = "logic")]
+#[derive(Debug, Clone, Default)]
+pub struct TypingThrottle {
+    /// When the last broadcast `TYPING_START` of every user was sent.
+    last: HashMap<Id, u64>,
+}
+
+#[cfg(feature = "logic")]
+impl TypingThrottle {
+    /// Create a new empty [`TypingThrottle`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle a `TYPING` payload from a user at `now`, returning the `TYPING_START` payload to
+    /// broadcast unless it's throttled.
+    pub fn typing(&mut self, user_id: Id, now: u64) -> Option<ServerPayload> {
+        match self.last.get(&user_id) {
+            Some(last) if now < last + TYPING_THROTTLE => None,
+            _ => {
+                self.last.insert(user_id, now);
+                Some(ServerPayload::TypingStart {
+                    user_id,
+                    timestamp: now,
+                })
+            }
+        }
+    }
+
+    /// Forget a user's typing indicator, this should be called when they send a message so that
+    /// they aren't throttled if they start typing again right away.
+    pub fn stop(&mut self, user_id: Id) {
+        self.last.remove(&user_id);
+    }
+
+    /// Forget every typing indicator that has expired by `now`, freeing up their memory.
+    pub fn clear_expired(&mut self, now: u64) {
+        self.last.retain(|_, last| *last + TYPING_TIMEOUT > now);
+    }
+}
+
+/// A client-side tracker of which users are typing.
+#[derive(Debug, Clone, Default)]
+pub struct TypingTracker {
+    /// When the typing indicator of every user expires.
+    expires_at: HashMap<Id, u64>,
+}
+
+impl TypingTracker {
+    /// Create a new empty [`TypingTracker`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the tracker with a payload from the server.
+    ///
+    /// `TYPING_START` payloads mark their user as typing and `MESSAGE_CREATE` payloads stop their
+    /// author's typing indicator.
+    pub fn handle(&mut self, payload: &ServerPayload) {
+        match payload {
+            ServerPayload::TypingStart { user_id, timestamp } => {
+                self.expires_at.insert(*user_id, timestamp + TYPING_TIMEOUT);
+            }
+            ServerPayload::MessageCreate(message) => {
+                self.expires_at.remove(&message.author.id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a user is typing at `now`.
+    pub fn is_typing(&self, user_id: Id, now: u64) -> bool {
+        self.expires_at
+            .get(&user_id)
+            .is_some_and(|expires_at| *expires_at > now)
+    }
+
+    /// Get the IDs of the users typing at `now`.
+    pub fn typing(&self, now: u64) -> impl Iterator<Item = Id> + '_ {
+        self.expires_at
+            .iter()
+            .filter(move |(_, expires_at)| **expires_at > now)
+            .map(|(user_id, _)| *user_id)
+    }
+
+    /// Get when the next typing indicator expires after `now`, which is when the typing users
+    /// change next unless another payload comes in.
+    pub fn next_expiry(&self, now: u64) -> Option<u64> {
+        self.expires_at
+            .values()
+            .filter(|expires_at| **expires_at > now)
+            .min()
+            .copied()
+    }
+
+    /// Forget every typing indicator that has expired by `now`.
+    pub fn clear_expired(&mut self, now: u64) {
+        self.expires_at.retain(|_, expires_at| *expires_at > now);
+    }
+}
@@ -0,0 +1,286 @@
+#[cfg(feature = "logic")]
+use std::sync::{Mutex, PoisonError};
+use std::{
+    fmt,
+    num::ParseIntError,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The Eludris epoch in seconds since the UNIX epoch, 2022-04-15T05:20:00Z.
+pub const ELUDRIS_EPOCH: u64 = 1_650_000_000;
+
+/// An Eludris ID.
+///
+/// IDs are time-ordered 64 bit integers made of a 48 bit timestamp in seconds since the
+/// [`ELUDRIS_EPOCH`], an 8 bit worker ID and an 8 bit sequence number.
+///
+/// IDs are serialized as numbers, or as strings with the `string-ids` feature since JavaScript
+/// can't represent integers above 2^53 precisely. Both forms are always accepted when
+/// deserializing.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```json
+/// 2312155037697
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(u64);
+
+impl Id {
+    /// Create a new [`Id`] from its parts.
+    ///
+    /// `timestamp` is in seconds since the [`ELUDRIS_EPOCH`] and is truncated to 48 bits.
+    pub const fn new(timestamp: u64, worker_id: u8, sequence: u8) -> Self {
+        Self((timestamp & 0xFFFF_FFFF_FFFF) << 16 | (worker_id as u64) << 8 | sequence as u64)
+    }
+
+    /// Get the [`Id`] as a [`u64`].
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    /// Get the ID's timestamp in seconds since the [`ELUDRIS_EPOCH`].
+    pub const fn timestamp(self) -> u64 {
+        self.0 >> 16
+    }
+
+    /// Get the ID's creation time in seconds since the UNIX epoch.
+    pub const fn unix_timestamp(self) -> u64 {
+        self.timestamp() + ELUDRIS_EPOCH
+    }
+
+    /// Get the ID's creation time.
+    pub fn created_at(self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.unix_timestamp())
+    }
+
+    /// Get the ID of the worker that generated the ID.
+    pub const fn worker_id(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    /// Get the ID's sequence number.
+    ///
+    /// This tells apart IDs generated by the same worker within the same second.
+    pub const fn sequence(self) -> u8 {
+        self.0 as u8
+    }
+}
+
+impl From<u64> for Id {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<Id> for u64 {
+    fn from(id: Id) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Id {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+impl Serialize for Id {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[cfg(feature = "string-ids")]
+        return serializer.collect_str(self);
+        #[cfg(not(feature = "string-ids"))]
+        serializer.serialize_u64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(IdVisitor)
+    }
+}
+
+struct IdVisitor;
+
+impl de::Visitor<'_> for IdVisitor {
+    type Value = Id;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an ID as a number or a string")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Id(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        u64::try_from(v)
+            .map(Id)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+/// A thread-safe [`Id`] generator.
+///
+/// Every worker generating IDs for the same instance needs its own worker ID. A worker can
+/// generate 256 IDs per second, after which it borrows seconds from the future so that IDs stay
+/// unique and ordered.
+#[cfg(feature = "logic")]
+#[derive(Debug)]
+pub struct IdGenerator {
+    worker_id: u8,
+    /// The timestamp and sequence number of the last generated ID.
+    last: Mutex<Option<(u64, u8)>>,
+}
+
+#[cfg(feature = "logic")]
+impl IdGenerator {
+    /// Create a new [`IdGenerator`] for a worker.
+    pub fn new(worker_id: u8) -> Self {
+        Self {
+            worker_id,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Get the generator's worker ID.
+    pub fn worker_id(&self) -> u8 {
+        self.worker_id
+    }
+
+    /// Generate a new [`Id`].
+    pub fn generate(&self) -> Id {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        self.generate_at(now.saturating_sub(ELUDRIS_EPOCH))
+    }
+
+    /// Generate a new [`Id`] at `timestamp` seconds since the [`ELUDRIS_EPOCH`].
+    ///
+    /// Generated IDs never go backwards, even if `timestamp` does.
+    pub fn generate_at(&self, timestamp: u64) -> Id {
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        let (timestamp, sequence) = match *last {
+            Some((last_timestamp, sequence)) if last_timestamp >= timestamp => {
+                match sequence.checked_add(1) {
+                    Some(sequence) => (last_timestamp, sequence),
+                    None => (last_timestamp + 1, 0),
+                }
+            }
+            _ => (timestamp, 0),
+        };
+        *last = Some((timestamp, sequence));
+        Id::new(timestamp, self.worker_id, sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(feature = "string-ids"))]
+    fn serializes_to_number() {
+        let id = Id::from(2312155037697);
+
+        assert_eq!(serde_json::to_string(&id).unwrap(), "2312155037697");
+        assert_eq!(
+            serde_json::to_string(&Id::from(u64::MAX)).unwrap(),
+            "18446744073709551615"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "string-ids")]
+    fn serializes_to_string() {
+        let id = Id::from(2312155037697);
+
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"2312155037697\"");
+        assert_eq!(
+            serde_json::to_string(&Id::from(u64::MAX)).unwrap(),
+            "\"18446744073709551615\""
+        );
+    }
+
+    #[test]
+    fn deserializes_numbers_and_strings() {
+        let id = Id::from(2312155037697);
+
+        assert_eq!(serde_json::from_str::<Id>("2312155037697").unwrap(), id);
+        assert_eq!(serde_json::from_str::<Id>("\"2312155037697\"").unwrap(), id);
+        assert_eq!(
+            serde_json::from_str::<Id>(&serde_json::to_string(&id).unwrap()).unwrap(),
+            id
+        );
+
+        assert!(serde_json::from_str::<Id>("-1").is_err());
+        assert!(serde_json::from_str::<Id>("\"-1\"").is_err());
+        assert!(serde_json::from_str::<Id>("\"yendri\"").is_err());
+        assert!(serde_json::from_str::<Id>("1.5").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "logic")]
+    fn sequence_rolls_over() {
+        let generator = IdGenerator::new(3);
+
+        let ids: Vec<Id> = (0..258).map(|_| generator.generate_at(100)).collect();
+        for (sequence, id) in ids[..256].iter().enumerate() {
+            assert_eq!(*id, Id::new(100, 3, sequence as u8));
+        }
+        // The 257th ID borrows the next second.
+        assert_eq!(ids[256], Id::new(101, 3, 0));
+        assert_eq!(ids[257], Id::new(101, 3, 1));
+
+        // Once the clock catches up the borrowed second is shared.
+        assert_eq!(generator.generate_at(101), Id::new(101, 3, 2));
+        assert_eq!(generator.generate_at(102), Id::new(102, 3, 0));
+    }
+
+    #[test]
+    #[cfg(feature = "logic")]
+    fn never_goes_backwards() {
+        let generator = IdGenerator::new(7);
+
+        let first = generator.generate_at(1_000);
+        let second = generator.generate_at(500);
+        let third = generator.generate_at(999);
+        assert_eq!(first, Id::new(1_000, 7, 0));
+        assert_eq!(second, Id::new(1_000, 7, 1));
+        assert_eq!(third, Id::new(1_000, 7, 2));
+
+        let mut last = third;
+        for timestamp in [1_000, 2_000, 0, 1_999, 2_000, 2_001, 10] {
+            let id = generator.generate_at(timestamp);
+            assert!(id > last, "{:?} isn't after {:?}", id, last);
+            assert_eq!(id.worker_id(), 7);
+            last = id;
+        }
+    }
+}
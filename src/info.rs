@@ -1,4 +1,4 @@
-use crate::conf::{EffisRateLimits, OprishRateLimits, RateLimitConf};
+use crate::conf::{Conf, EffisRateLimits, OprishRateLimits, RateLimitConf};
 use serde::{Deserialize, Serialize};
 
 /// Represents information about the connected Eludris instance.
@@ -91,6 +91,28 @@ pub struct InstanceInfo {
     pub rate_limits: Option<InstanceRateLimits>,
 }
 
+impl InstanceInfo {
+    /// Create a new [`InstanceInfo`] from the instance's [`Conf`].
+    ///
+    /// The instance's rate limits are only included if `rate_limits` is true.
+    pub fn from_conf(conf: &Conf, rate_limits: bool) -> Self {
+        Self {
+            instance_name: conf.instance_name.clone(),
+            description: conf.description.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            message_limit: conf.oprish.message_limit,
+            bio_limit: conf.oprish.bio_limit,
+            oprish_url: conf.oprish.url.clone(),
+            pandemonium_url: conf.pandemonium.url.clone(),
+            effis_url: conf.effis.url.clone(),
+            file_size: conf.effis.file_size,
+            attachment_file_size: conf.effis.attachment_file_size,
+            email_address: conf.email_address.clone(),
+            rate_limits: rate_limits.then(|| InstanceRateLimits::from(conf)),
+        }
+    }
+}
+
 /// Represents all rate limits that apply to the connected Eludris instance.
 ///
 /// -----
@@ -144,3 +166,13 @@ pub struct InstanceRateLimits {
     /// The instance's Effis rate limit information (The CDN).
     pub effis: EffisRateLimits,
 }
+
+impl From<&Conf> for InstanceRateLimits {
+    fn from(conf: &Conf) -> Self {
+        Self {
+            oprish: conf.oprish.rate_limits.clone(),
+            pandemonium: conf.pandemonium.rate_limit.clone(),
+            effis: conf.effis.rate_limits.clone(),
+        }
+    }
+}
@@ -1,17 +1,30 @@
 //! A simple crate with Eludris models
+//!
+//! The gateway and rate limiting state machines don't do any IO themselves, they're passed the
+//! current time as `now`. Unless stated otherwise, times are in milliseconds since the UNIX epoch.
 
+#[cfg(feature = "logic")]
+mod email;
 mod files;
+#[cfg(test)]
+mod fixtures;
 mod gateway;
+mod ids;
 mod info;
 mod messages;
+mod rate_limit;
 mod response;
 mod sessions;
 mod users;
 
+#[cfg(feature = "logic")]
+pub use email::*;
 pub use files::*;
 pub use gateway::*;
+pub use ids::*;
 pub use info::*;
 pub use messages::*;
+pub use rate_limit::*;
 pub use response::*;
 pub use sessions::*;
 pub use users::*;
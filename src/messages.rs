@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use super::User;
+use super::{Id, User};
 
 /// The MessageCreate payload. This is used when you want to create a message using the REST API.
 ///
@@ -55,6 +55,7 @@ pub struct MessageDisguise {
 ///
 /// ```json
 /// {
+///   "id": 2312155037697,
 ///   "author": {
 ///      "id": 48615849987333,
 ///      "username": "mlynar",
@@ -67,9 +68,36 @@ pub struct MessageDisguise {
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
+    /// The message's ID.
+    pub id: Id,
     /// The message's author.
     pub author: User,
     /// There message's data.
     #[serde(flatten)]
     pub message: MessageCreate,
 }
+
+/// The MessageEdit payload. This is used when you want to edit one of your messages using the
+/// REST API.
+///
+/// Every field is optional, only the ones that are present are changed. This is also the payload
+/// of the `MESSAGE_UPDATE` gateway event, where it holds the fields that were changed.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```json
+/// {
+///   "content": "Hello, Eludris!"
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageEdit {
+    /// The message's new content. This field has to be at-least 2 characters long. The upper
+    /// limit is the instance's [`InstanceInfo`] `message_limit`.
+    ///
+    /// The content will be trimmed from leading and trailing whitespace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
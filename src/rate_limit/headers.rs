@@ -0,0 +1,123 @@
+use super::{RateLimitDecision, RateLimitStatus};
+
+/// The rate limit headers Eludris HTTP microservices send with their responses.
+///
+/// -----
+///
+/// ### Example
+///
+/// ```http
+/// X-RateLimit-Limit: 10
+/// X-RateLimit-Remaining: 0
+/// X-RateLimit-Reset: 1234
+/// Retry-After: 2
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitHeaders {
+    /// The amount of requests that can be made within the bucket's interval.
+    pub limit: u32,
+    /// The amount of requests that can still be made before the bucket resets.
+    pub remaining: u32,
+    /// The amount of milliseconds until the bucket resets.
+    pub reset_after: u64,
+    /// The amount of bytes that can still be uploaded before the bucket resets.
+    ///
+    /// This is only present for buckets with a byte budget.
+    pub remaining_bytes: Option<u64>,
+    /// The amount of seconds the client has to wait before retrying, rounded up.
+    ///
+    /// This is only present when the client is rate limited, `reset_after` is more precise.
+    pub retry_after: Option<u64>,
+}
+
+impl RateLimitHeaders {
+    /// The name of the header holding the bucket's limit.
+    pub const LIMIT: &'static str = "X-RateLimit-Limit";
+    /// The name of the header holding the bucket's remaining requests.
+    pub const REMAINING: &'static str = "X-RateLimit-Remaining";
+    /// The name of the header holding the amount of milliseconds until the bucket resets.
+    pub const RESET: &'static str = "X-RateLimit-Reset";
+    /// The name of the header holding the bucket's remaining bytes.
+    pub const REMAINING_BYTES: &'static str = "X-RateLimit-Remaining-Bytes";
+    /// The name of the header holding the amount of seconds to wait before retrying.
+    pub const RETRY_AFTER: &'static str = "Retry-After";
+
+    /// Render the headers as name and value pairs.
+    pub fn to_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            (Self::LIMIT, self.limit.to_string()),
+            (Self::REMAINING, self.remaining.to_string()),
+            (Self::RESET, self.reset_after.to_string()),
+        ];
+        if let Some(remaining_bytes) = self.remaining_bytes {
+            headers.push((Self::REMAINING_BYTES, remaining_bytes.to_string()));
+        }
+        if let Some(retry_after) = self.retry_after {
+            headers.push((Self::RETRY_AFTER, retry_after.to_string()));
+        }
+        headers
+    }
+
+    /// Parse the headers out of a response's header name and value pairs.
+    ///
+    /// Header names are matched case-insensitively. Returns `None` if any of the limit, remaining
+    /// or reset headers is missing or invalid.
+    pub fn from_headers<'a, I>(headers: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let (mut limit, mut remaining, mut reset_after, mut remaining_bytes, mut retry_after) =
+            (None, None, None, None, None);
+        for (name, value) in headers {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case(Self::LIMIT) {
+                limit = Some(value.parse().ok()?);
+            } else if name.eq_ignore_ascii_case(Self::REMAINING) {
+                remaining = Some(value.parse().ok()?);
+            } else if name.eq_ignore_ascii_case(Self::RESET) {
+                reset_after = Some(value.parse().ok()?);
+            } else if name.eq_ignore_ascii_case(Self::REMAINING_BYTES) {
+                remaining_bytes = value.parse().ok();
+            } else if name.eq_ignore_ascii_case(Self::RETRY_AFTER) {
+                // This can also be an HTTP date, which Eludris never sends.
+                retry_after = value.parse().ok();
+            }
+        }
+        Some(Self {
+            limit: limit?,
+            remaining: remaining?,
+            reset_after: reset_after?,
+            remaining_bytes,
+            retry_after,
+        })
+    }
+
+    /// Whether the headers say that the client is rate limited.
+    pub fn is_rate_limited(&self) -> bool {
+        self.retry_after.is_some()
+    }
+}
+
+impl From<&RateLimitStatus> for RateLimitHeaders {
+    fn from(status: &RateLimitStatus) -> Self {
+        Self {
+            limit: status.limit,
+            remaining: status.remaining,
+            reset_after: status.reset_after,
+            remaining_bytes: status.remaining_bytes,
+            retry_after: None,
+        }
+    }
+}
+
+impl From<&RateLimitDecision> for RateLimitHeaders {
+    fn from(decision: &RateLimitDecision) -> Self {
+        match decision {
+            RateLimitDecision::Allowed(status) => status.into(),
+            RateLimitDecision::Limited(status) => Self {
+                retry_after: Some(status.reset_after.div_ceil(1000)),
+                ..status.into()
+            },
+        }
+    }
+}
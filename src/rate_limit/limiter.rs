@@ -0,0 +1,372 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{MemoryStore, RateLimitDecision, RateLimitStatus, RateLimitStore, WindowHit};
+use crate::conf::{EffisRateLimitConf, RateLimitConf};
+
+/// A source of the current time for rate limiters.
+pub trait Clock: Send + Sync {
+    /// Get the current time in milliseconds since the UNIX epoch.
+    fn now(&self) -> u64;
+}
+
+/// A [`Clock`] backed by the system's time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// A [`Clock`] that only moves when told to, mainly useful for testing.
+#[derive(Debug, Default)]
+pub struct ManualClock(AtomicU64);
+
+impl ManualClock {
+    /// Create a new [`ManualClock`] starting at `now` milliseconds since the UNIX epoch.
+    pub fn new(now: u64) -> Self {
+        Self(AtomicU64::new(now))
+    }
+
+    /// Set the clock's time.
+    pub fn set(&self, now: u64) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    /// Move the clock forward by `millis` milliseconds.
+    pub fn advance(&self, millis: u64) {
+        self.0.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
+/// A fixed window rate limiter.
+///
+/// Every `(bucket, identifier)` pair gets its own window which starts on its first request and
+/// lasts for the [`RateLimitConf`]'s `reset_after`. Windows are kept in a [`RateLimitStore`],
+/// which is a [`MemoryStore`] by default.
+#[derive(Debug, Default)]
+pub struct RateLimiter<S = MemoryStore, C = SystemClock> {
+    store: S,
+    clock: C,
+}
+
+impl RateLimiter {
+    /// Create a new [`RateLimiter`] that keeps its windows in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: RateLimitStore> RateLimiter<S> {
+    /// Create a new [`RateLimiter`] that keeps its windows in a custom [`RateLimitStore`].
+    pub fn with_store(store: S) -> Self {
+        Self {
+            store,
+            clock: SystemClock,
+        }
+    }
+}
+
+impl<S: RateLimitStore, C: Clock> RateLimiter<S, C> {
+    /// Use a custom [`Clock`] for the rate limiter.
+    pub fn with_clock<T: Clock>(self, clock: T) -> RateLimiter<S, T> {
+        RateLimiter {
+            store: self.store,
+            clock,
+        }
+    }
+
+    /// Get the rate limiter's [`RateLimitStore`].
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Check a request from `identifier` against the `bucket`'s rate limit, counting it if it's
+    /// allowed.
+    pub async fn check(
+        &self,
+        bucket: &str,
+        identifier: &str,
+        conf: &RateLimitConf,
+    ) -> Result<RateLimitDecision, S::Error> {
+        self.hit(bucket, identifier, conf.reset_after, conf.limit, None)
+            .await
+    }
+
+    /// Check an upload of `size` bytes from `identifier` against the `bucket`'s rate limit,
+    /// counting both the request and its bytes if it's allowed.
+    ///
+    /// This is meant to be called with the upload's declared length before its body is consumed.
+    /// The upload is rate limited if it would go over either the request limit or the
+    /// `file_size_limit` byte budget of the current window.
+    pub async fn check_upload(
+        &self,
+        bucket: &str,
+        identifier: &str,
+        conf: &EffisRateLimitConf,
+        size: u64,
+    ) -> Result<RateLimitDecision, S::Error> {
+        self.hit(
+            bucket,
+            identifier,
+            conf.reset_after,
+            conf.limit,
+            Some((size, conf.file_size_limit)),
+        )
+        .await
+    }
+
+    /// Reset the rate limit of `identifier` in the `bucket`.
+    pub async fn reset(&self, bucket: &str, identifier: &str) -> Result<(), S::Error> {
+        self.store.reset(&window_key(bucket, identifier)).await
+    }
+
+    async fn hit(
+        &self,
+        bucket: &str,
+        identifier: &str,
+        reset_after: Duration,
+        limit: u32,
+        bytes: Option<(u64, u64)>,
+    ) -> Result<RateLimitDecision, S::Error> {
+        let now = self.clock.now();
+        let window = self
+            .store
+            .hit(WindowHit {
+                key: &window_key(bucket, identifier),
+                now,
+                reset_after: reset_after.as_millis() as u64,
+                limit,
+                bytes,
+            })
+            .await?;
+
+        let status = RateLimitStatus {
+            limit,
+            remaining: limit.saturating_sub(window.count),
+            remaining_bytes: bytes.map(|(_, byte_limit)| byte_limit.saturating_sub(window.bytes)),
+            reset_after: window.reset_at.saturating_sub(now),
+        };
+        Ok(if window.allowed {
+            RateLimitDecision::Allowed(status)
+        } else {
+            RateLimitDecision::Limited(status)
+        })
+    }
+}
+
+impl<C: Clock> RateLimiter<MemoryStore, C> {
+    /// Forget every window that has already reset, freeing up their memory.
+    pub fn clear_expired(&self) {
+        self.store.clear_expired(self.clock.now());
+    }
+}
+
+/// Get the store key of a `(bucket, identifier)` pair's window.
+///
+/// The bucket is prefixed with its length so that pairs can't collide, like `("a:b", "c")` and
+/// `("a", "b:c")`.
+fn window_key(bucket: &str, identifier: &str) -> String {
+    format!("{}:{}:{}", bucket.len(), bucket, identifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorResponse;
+
+    fn conf(limit: u32) -> RateLimitConf {
+        RateLimitConf {
+            reset_after: Duration::from_secs(10),
+            limit,
+        }
+    }
+
+    fn limiter(clock: &ManualClock) -> RateLimiter<MemoryStore, &ManualClock> {
+        RateLimiter::new().with_clock(clock)
+    }
+
+    #[tokio::test]
+    async fn exhausts_limit() {
+        let clock = ManualClock::new(1_000);
+        let limiter = limiter(&clock);
+        let conf = conf(3);
+
+        for remaining in (0..3).rev() {
+            let decision = limiter.check("bucket", "user", &conf).await.unwrap();
+            assert!(decision.is_allowed());
+            assert_eq!(decision.status().remaining, remaining);
+            assert_eq!(decision.status().reset_after, 10_000);
+        }
+
+        clock.advance(4_000);
+        let decision = limiter.check("bucket", "user", &conf).await.unwrap();
+        assert_eq!(
+            decision,
+            RateLimitDecision::Limited(RateLimitStatus {
+                limit: 3,
+                remaining: 0,
+                remaining_bytes: None,
+                reset_after: 6_000,
+            })
+        );
+
+        // Other identifiers and buckets have their own windows.
+        assert!(limiter
+            .check("bucket", "other", &conf)
+            .await
+            .unwrap()
+            .is_allowed());
+        assert!(limiter
+            .check("other", "user", &conf)
+            .await
+            .unwrap()
+            .is_allowed());
+    }
+
+    #[tokio::test]
+    async fn into_result_retry_after() {
+        let clock = ManualClock::new(1_000);
+        let limiter = limiter(&clock);
+        let conf = conf(1);
+
+        let status = limiter
+            .check("bucket", "user", &conf)
+            .await
+            .unwrap()
+            .into_result()
+            .unwrap();
+        assert_eq!(status.remaining, 0);
+
+        clock.advance(2_500);
+        match limiter
+            .check("bucket", "user", &conf)
+            .await
+            .unwrap()
+            .into_result()
+        {
+            Err(ErrorResponse::RateLimited {
+                shared,
+                retry_after,
+            }) => {
+                assert_eq!(shared.status, 429);
+                assert_eq!(retry_after, 7_500);
+            }
+            result => panic!("Expected a rate limit error, got {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn window_resets_after_advance() {
+        let clock = ManualClock::new(1_000);
+        let limiter = limiter(&clock);
+        let conf = conf(1);
+
+        assert!(limiter
+            .check("bucket", "user", &conf)
+            .await
+            .unwrap()
+            .is_allowed());
+        clock.advance(9_999);
+        assert!(!limiter
+            .check("bucket", "user", &conf)
+            .await
+            .unwrap()
+            .is_allowed());
+
+        clock.advance(1);
+        let decision = limiter.check("bucket", "user", &conf).await.unwrap();
+        assert!(decision.is_allowed());
+        assert_eq!(decision.status().reset_after, 10_000);
+    }
+
+    #[tokio::test]
+    async fn upload_byte_budget() {
+        let clock = ManualClock::new(1_000);
+        let limiter = limiter(&clock);
+        let conf = EffisRateLimitConf {
+            reset_after: Duration::from_secs(10),
+            limit: 10,
+            file_size_limit: 100,
+        };
+
+        let decision = limiter
+            .check_upload("assets", "user", &conf, 60)
+            .await
+            .unwrap();
+        assert!(decision.is_allowed());
+        assert_eq!(decision.status().remaining_bytes, Some(40));
+
+        let decision = limiter
+            .check_upload("assets", "user", &conf, 50)
+            .await
+            .unwrap();
+        assert!(!decision.is_allowed());
+        assert_eq!(decision.status().remaining, 9);
+        assert_eq!(decision.status().remaining_bytes, Some(40));
+
+        assert!(limiter
+            .check_upload("assets", "user", &conf, 40)
+            .await
+            .unwrap()
+            .is_allowed());
+    }
+
+    #[tokio::test]
+    async fn windows_do_not_collide() {
+        let clock = ManualClock::new(1_000);
+        let limiter = limiter(&clock);
+        let conf = conf(1);
+
+        assert_ne!(window_key("a:b", "c"), window_key("a", "b:c"));
+        assert!(limiter.check("a:b", "c", &conf).await.unwrap().is_allowed());
+        assert!(limiter.check("a", "b:c", &conf).await.unwrap().is_allowed());
+    }
+
+    #[tokio::test]
+    async fn reset_forgets_window() {
+        let clock = ManualClock::new(1_000);
+        let limiter = limiter(&clock);
+        let conf = conf(1);
+
+        assert!(limiter
+            .check("bucket", "user", &conf)
+            .await
+            .unwrap()
+            .is_allowed());
+        limiter.reset("bucket", "user").await.unwrap();
+        assert!(limiter
+            .check("bucket", "user", &conf)
+            .await
+            .unwrap()
+            .is_allowed());
+    }
+}
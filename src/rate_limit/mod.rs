@@ -0,0 +1,72 @@
+mod headers;
+#[cfg(feature = "logic")]
+mod limiter;
+#[cfg(feature = "logic")]
+mod redis_store;
+mod scheduler;
+#[cfg(feature = "logic")]
+mod store;
+
+pub use headers::*;
+#[cfg(feature = "logic")]
+pub use limiter::*;
+#[cfg(feature = "logic")]
+pub use redis_store::*;
+pub use scheduler::*;
+#[cfg(feature = "logic")]
+pub use store::*;
+
+use super::{ErrorResponse, SharedErrorData};
+
+/// The state of a rate limit bucket after a request was accounted for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// The amount of requests that can be made within the bucket's interval.
+    pub limit: u32,
+    /// The amount of requests that can still be made before the bucket resets.
+    pub remaining: u32,
+    /// The amount of bytes that can still be uploaded before the bucket resets.
+    ///
+    /// This is only present for buckets with a byte budget, like Effis' upload buckets.
+    pub remaining_bytes: Option<u64>,
+    /// The amount of milliseconds until the bucket resets.
+    pub reset_after: u64,
+}
+
+/// The outcome of checking a request against a rate limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    /// The request is allowed and was counted towards the rate limit.
+    Allowed(RateLimitStatus),
+    /// The request is rate limited and was not counted towards the rate limit.
+    Limited(RateLimitStatus),
+}
+
+impl RateLimitDecision {
+    /// Get the state of the rate limit bucket.
+    pub fn status(&self) -> &RateLimitStatus {
+        match self {
+            Self::Allowed(status) | Self::Limited(status) => status,
+        }
+    }
+
+    /// Whether the request is allowed.
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allowed(_))
+    }
+
+    /// Turn the decision into a [`Result`], with rate limited requests being turned into an
+    /// [`ErrorResponse::RateLimited`].
+    pub fn into_result(self) -> Result<RateLimitStatus, ErrorResponse> {
+        match self {
+            Self::Allowed(status) => Ok(status),
+            Self::Limited(status) => Err(ErrorResponse::RateLimited {
+                shared: SharedErrorData {
+                    status: 429,
+                    message: "You have been rate limited".to_string(),
+                },
+                retry_after: status.reset_after,
+            }),
+        }
+    }
+}
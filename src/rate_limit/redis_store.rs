@@ -0,0 +1,167 @@
+use redis::{aio::ConnectionManager, RedisError, Script};
+
+use super::{RateLimitStore, WindowHit, WindowState};
+
+/// The script that atomically checks and updates a window.
+///
+/// Windows are stored as hashes which expire when the window resets.
+const HIT_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local reset_after = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+local size = tonumber(ARGV[4])
+local byte_limit = tonumber(ARGV[5])
+
+local window = redis.call("HMGET", KEYS[1], "reset_at", "count", "bytes")
+local reset_at = tonumber(window[1])
+local count = tonumber(window[2]) or 0
+local bytes = tonumber(window[3]) or 0
+if reset_at == nil or reset_at <= now then
+  reset_at = now + reset_after
+  count = 0
+  bytes = 0
+end
+
+local allowed = count < limit and (byte_limit < 0 or bytes + size <= byte_limit)
+if allowed then
+  count = count + 1
+  bytes = bytes + size
+end
+redis.call("HSET", KEYS[1], "reset_at", reset_at, "count", count, "bytes", bytes)
+redis.call("PEXPIRE", KEYS[1], reset_at - now)
+
+return {allowed and 1 or 0, count, bytes, reset_at}
+"#;
+
+/// A [`RateLimitStore`] backed by Redis, or anything else that speaks its protocol.
+///
+/// Windows are updated using a Lua script so that a single Redis instance can be shared by
+/// multiple replicas of a service.
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: ConnectionManager,
+    prefix: String,
+    script: Script,
+}
+
+impl RedisStore {
+    /// Create a new [`RedisStore`], prefixing all window keys with `rate_limit:`.
+    pub fn new(connection: ConnectionManager) -> Self {
+        Self::with_prefix(connection, "rate_limit:")
+    }
+
+    /// Create a new [`RedisStore`] with a custom key prefix.
+    pub fn with_prefix<T: Into<String>>(connection: ConnectionManager, prefix: T) -> Self {
+        Self {
+            connection,
+            prefix: prefix.into(),
+            script: Script::new(HIT_SCRIPT),
+        }
+    }
+}
+
+impl RateLimitStore for RedisStore {
+    type Error = RedisError;
+
+    async fn hit(&self, hit: WindowHit<'_>) -> Result<WindowState, Self::Error> {
+        let (size, byte_limit) = match hit.bytes {
+            Some((size, byte_limit)) => (size, byte_limit.min(i64::MAX as u64) as i64),
+            None => (0, -1),
+        };
+        let (allowed, count, bytes, reset_at): (u8, u32, u64, u64) = self
+            .script
+            .key(format!("{}{}", self.prefix, hit.key))
+            .arg(hit.now)
+            .arg(hit.reset_after)
+            .arg(hit.limit)
+            .arg(size)
+            .arg(byte_limit)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        Ok(WindowState {
+            allowed: allowed == 1,
+            count,
+            bytes,
+            reset_at,
+        })
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), Self::Error> {
+        redis::cmd("DEL")
+            .arg(format!("{}{}", self.prefix, key))
+            .exec_async(&mut self.connection.clone())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        process::{Child, Command, Stdio},
+    };
+
+    use redis::{
+        aio::{ConnectionManager, ConnectionManagerConfig},
+        Client,
+    };
+
+    use super::*;
+    use crate::rate_limit::store::tests::conformance;
+
+    /// A `redis-server` child process that's killed when dropped.
+    struct RedisServer {
+        child: Child,
+        port: u16,
+    }
+
+    impl RedisServer {
+        fn start() -> Self {
+            let port = TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            let child = Command::new("redis-server")
+                .args([
+                    "--port",
+                    &port.to_string(),
+                    "--save",
+                    "",
+                    "--appendonly",
+                    "no",
+                ])
+                .stdout(Stdio::null())
+                .spawn()
+                .expect("Could not start redis-server");
+            Self { child, port }
+        }
+
+        async fn connect(&self) -> ConnectionManager {
+            let client = Client::open(format!("redis://127.0.0.1:{}", self.port)).unwrap();
+            let config = ConnectionManagerConfig::new()
+                .set_number_of_retries(50)
+                .set_factor(10)
+                .set_max_delay(100);
+            ConnectionManager::new_with_config(client, config)
+                .await
+                .expect("Could not connect to redis-server")
+        }
+    }
+
+    impl Drop for RedisServer {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires redis-server"]
+    async fn redis_store() {
+        let server = RedisServer::start();
+        let store = RedisStore::new(server.connect().await);
+
+        conformance(&store).await;
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+use super::RateLimitHeaders;
+use crate::{
+    conf::{EffisRoute, OprishRoute},
+    ErrorResponse, InstanceRateLimits,
+};
+
+/// A rate limit bucket that requests can be scheduled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RateLimitBucket {
+    /// An Oprish route's bucket.
+    Oprish(OprishRoute),
+    /// An Effis bucket.
+    Effis(EffisRoute),
+}
+
+impl From<OprishRoute> for RateLimitBucket {
+    fn from(route: OprishRoute) -> Self {
+        Self::Oprish(route)
+    }
+}
+
+impl From<EffisRoute> for RateLimitBucket {
+    fn from(route: EffisRoute) -> Self {
+        Self::Effis(route)
+    }
+}
+
+/// A client side scheduler that predicts an instance's rate limits.
+///
+/// Clients ask the scheduler how long to wait before sending a request and feed it what the server
+/// responds with. Every scheduled request reserves a slot in its bucket's window, so requests
+/// that are scheduled together are queued behind each other instead of all being sent at once.
+///
+/// The prediction is based on the instance's [`InstanceRateLimits`] and corrected with the
+/// [`RateLimitHeaders`] of every response. An [`ErrorResponse::RateLimited`] always overrides the
+/// prediction with its `retry_after`.
+#[derive(Debug, Clone)]
+pub struct RateLimitScheduler {
+    rate_limits: InstanceRateLimits,
+    windows: HashMap<RateLimitBucket, Window>,
+}
+
+/// The window the scheduler is currently handing out slots from.
+///
+/// This may be a window in the future once the current one has been filled up.
+#[derive(Debug, Clone, Default)]
+struct Window {
+    start: u64,
+    reset_at: u64,
+    count: u32,
+    bytes: u64,
+    /// The bucket's limit as reported by the server, which can differ from the instance's
+    /// advertised one.
+    limit: Option<u32>,
+}
+
+impl RateLimitScheduler {
+    /// Create a new [`RateLimitScheduler`] for an instance's rate limits.
+    pub fn new(rate_limits: InstanceRateLimits) -> Self {
+        Self {
+            rate_limits,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Get the instance rate limits the scheduler is predicting.
+    pub fn rate_limits(&self) -> &InstanceRateLimits {
+        &self.rate_limits
+    }
+
+    /// Replace the instance rate limits the scheduler is predicting, keeping the state of its
+    /// buckets.
+    pub fn set_rate_limits(&mut self, rate_limits: InstanceRateLimits) {
+        self.rate_limits = rate_limits;
+    }
+
+    /// Reserve a slot for a request in the `bucket`, returning the amount of milliseconds to wait
+    /// before sending it.
+    pub fn schedule<B: Into<RateLimitBucket>>(&mut self, bucket: B, now: u64) -> u64 {
+        self.reserve(bucket.into(), 0, now)
+    }
+
+    /// Reserve a slot for an upload of `size` bytes in the `bucket`, returning the amount of
+    /// milliseconds to wait before sending it.
+    ///
+    /// This also accounts for the byte budget of Effis' upload buckets.
+    pub fn schedule_upload<B: Into<RateLimitBucket>>(
+        &mut self,
+        bucket: B,
+        size: u64,
+        now: u64,
+    ) -> u64 {
+        self.reserve(bucket.into(), size, now)
+    }
+
+    /// Correct the `bucket`'s prediction with the [`RateLimitHeaders`] of a response received at
+    /// `now`.
+    pub fn handle_headers<B: Into<RateLimitBucket>>(
+        &mut self,
+        bucket: B,
+        headers: &RateLimitHeaders,
+        now: u64,
+    ) {
+        let bucket = bucket.into();
+        if headers.is_rate_limited() {
+            self.block(bucket, now + headers.reset_after);
+            return;
+        }
+
+        let byte_limit = self.byte_limit(bucket);
+        let window = self.windows.entry(bucket).or_default();
+        window.limit = Some(headers.limit);
+        let reset_at = now + headers.reset_after;
+        let count = headers.limit.saturating_sub(headers.remaining);
+        let bytes = match (byte_limit, headers.remaining_bytes) {
+            (Some(byte_limit), Some(remaining_bytes)) => byte_limit.saturating_sub(remaining_bytes),
+            _ => 0,
+        };
+        if window.reset_at <= now {
+            window.start = now;
+            window.reset_at = reset_at;
+            window.count = count;
+            window.bytes = bytes;
+        } else if window.start <= now {
+            // Requests reserved in the current window may still be in flight, so the server's
+            // numbers can only ever make the prediction stricter.
+            window.reset_at = reset_at;
+            window.count = window.count.max(count);
+            window.bytes = window.bytes.max(bytes);
+        }
+    }
+
+    /// Handle an error response received at `now`, returning whether it was an
+    /// [`ErrorResponse::RateLimited`].
+    ///
+    /// The error's `retry_after` overrides the `bucket`'s prediction, no requests are scheduled
+    /// before it's over.
+    pub fn handle_error<B: Into<RateLimitBucket>>(
+        &mut self,
+        bucket: B,
+        error: &ErrorResponse,
+        now: u64,
+    ) -> bool {
+        match error {
+            ErrorResponse::RateLimited { retry_after, .. } => {
+                self.block(bucket.into(), now + retry_after);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forget the state of every bucket.
+    pub fn clear(&mut self) {
+        self.windows.clear();
+    }
+
+    fn reserve(&mut self, bucket: RateLimitBucket, size: u64, now: u64) -> u64 {
+        let (reset_after, limit) = self.rate_limit(bucket);
+        let byte_limit = self.byte_limit(bucket);
+        let window = self.windows.entry(bucket).or_default();
+        let limit = window.limit.unwrap_or(limit).max(1);
+
+        let at = now.max(window.start);
+        if at >= window.reset_at {
+            window.start = at;
+            window.reset_at = at + reset_after;
+            window.count = 0;
+            window.bytes = 0;
+        } else if window.count >= limit
+            || (window.count > 0
+                && byte_limit
+                    .is_some_and(|byte_limit| window.bytes.saturating_add(size) > byte_limit))
+        {
+            window.start = window.reset_at;
+            window.reset_at += reset_after;
+            window.count = 0;
+            window.bytes = 0;
+        }
+        window.count += 1;
+        window.bytes = window.bytes.saturating_add(size);
+
+        window.start.max(now) - now
+    }
+
+    fn block(&mut self, bucket: RateLimitBucket, until: u64) {
+        let window = self.windows.entry(bucket).or_default();
+        // Only ever push the bucket back, a stale error shouldn't let requests through early.
+        let until = until.max(window.start);
+        window.start = until;
+        window.reset_at = until;
+        window.count = 0;
+        window.bytes = 0;
+    }
+
+    fn rate_limit(&self, bucket: RateLimitBucket) -> (u64, u32) {
+        let (reset_after, limit) = match bucket {
+            RateLimitBucket::Oprish(route) => {
+                let rate_limit = self.rate_limits.oprish.get(route);
+                (rate_limit.reset_after, rate_limit.limit)
+            }
+            RateLimitBucket::Effis(EffisRoute::Assets) => (
+                self.rate_limits.effis.assets.reset_after,
+                self.rate_limits.effis.assets.limit,
+            ),
+            RateLimitBucket::Effis(EffisRoute::Attachments) => (
+                self.rate_limits.effis.attachments.reset_after,
+                self.rate_limits.effis.attachments.limit,
+            ),
+            RateLimitBucket::Effis(EffisRoute::FetchFile) => (
+                self.rate_limits.effis.fetch_file.reset_after,
+                self.rate_limits.effis.fetch_file.limit,
+            ),
+        };
+        (reset_after.as_millis() as u64, limit)
+    }
+
+    fn byte_limit(&self, bucket: RateLimitBucket) -> Option<u64> {
+        match bucket {
+            RateLimitBucket::Effis(EffisRoute::Assets) => {
+                Some(self.rate_limits.effis.assets.file_size_limit)
+            }
+            RateLimitBucket::Effis(EffisRoute::Attachments) => {
+                Some(self.rate_limits.effis.attachments.file_size_limit)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        conf::{RateLimitConf, RateLimitPreset},
+        SharedErrorData,
+    };
+
+    fn scheduler() -> RateLimitScheduler {
+        let preset = RateLimitPreset::default();
+        RateLimitScheduler::new(InstanceRateLimits {
+            oprish: preset.oprish_rate_limits(),
+            pandemonium: preset.pandemonium_rate_limit(),
+            effis: preset.effis_rate_limits(),
+        })
+    }
+
+    /// Get a scheduler whose message creation bucket allows 3 requests every 5 seconds.
+    fn messages() -> RateLimitScheduler {
+        let mut scheduler = scheduler();
+        let mut rate_limits = scheduler.rate_limits().clone();
+        rate_limits.oprish.create_message = RateLimitConf {
+            reset_after: Duration::from_secs(5),
+            limit: 3,
+        };
+        scheduler.set_rate_limits(rate_limits);
+        scheduler
+    }
+
+    fn headers(limit: u32, remaining: u32, reset_after: u64) -> RateLimitHeaders {
+        RateLimitHeaders {
+            limit,
+            remaining,
+            reset_after,
+            remaining_bytes: None,
+            retry_after: None,
+        }
+    }
+
+    fn rate_limited(retry_after: u64) -> ErrorResponse {
+        ErrorResponse::RateLimited {
+            shared: SharedErrorData {
+                status: 429,
+                message: "You have been rate limited".to_string(),
+            },
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn queues_into_next_windows() {
+        let mut scheduler = messages();
+
+        let waits: Vec<u64> = (0..7)
+            .map(|_| scheduler.schedule(OprishRoute::CreateMessage, 1_000))
+            .collect();
+        assert_eq!(waits, [0, 0, 0, 5_000, 5_000, 5_000, 10_000]);
+
+        // The 7th request opened the window starting at 11000, which still has 2 free slots.
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 7_000), 4_000);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 7_000), 4_000);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 7_000), 9_000);
+
+        // Other buckets aren't affected.
+        assert_eq!(scheduler.schedule(OprishRoute::EditMessage, 7_000), 0);
+
+        // Once every queued window is over the bucket starts from scratch.
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 30_000), 0);
+    }
+
+    #[test]
+    fn headers_tighten_prediction() {
+        let mut scheduler = messages();
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 0);
+
+        // Other clients used up the bucket and it resets sooner than predicted.
+        scheduler.handle_headers(OprishRoute::CreateMessage, &headers(3, 0, 2_000), 1_200);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_200), 2_000);
+
+        // Headers can't loosen a window that has requests in flight.
+        scheduler.handle_headers(OprishRoute::CreateMessage, &headers(3, 3, 2_000), 3_200);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 3_200), 0);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 3_200), 0);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 3_200), 2_000);
+
+        // The server's limit replaces the advertised one.
+        let mut scheduler = messages();
+        scheduler.handle_headers(OprishRoute::CreateMessage, &headers(2, 2, 1_000), 1_000);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 0);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 0);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 1_000);
+        // New windows are as long as the instance's advertised ones.
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 1_000);
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 6_000);
+    }
+
+    #[test]
+    fn rate_limits_override_prediction() {
+        let mut scheduler = messages();
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 0);
+
+        assert!(scheduler.handle_error(OprishRoute::CreateMessage, &rate_limited(30_000), 1_500));
+        assert_eq!(
+            scheduler.schedule(OprishRoute::CreateMessage, 2_000),
+            29_500
+        );
+
+        // A stale error doesn't let requests through early.
+        assert!(scheduler.handle_error(OprishRoute::CreateMessage, &rate_limited(1_000), 2_000));
+        assert_eq!(
+            scheduler.schedule(OprishRoute::CreateMessage, 2_000),
+            29_500
+        );
+
+        // Rate limited headers block the bucket the same way.
+        let mut scheduler = messages();
+        scheduler.handle_headers(
+            OprishRoute::CreateMessage,
+            &RateLimitHeaders {
+                retry_after: Some(8),
+                ..headers(3, 0, 7_500)
+            },
+            1_000,
+        );
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 7_500);
+
+        // Other errors don't affect the bucket.
+        let mut scheduler = messages();
+        let error = ErrorResponse::Unauthorized {
+            shared: SharedErrorData {
+                status: 401,
+                message: "The user is missing authentication".to_string(),
+            },
+        };
+        assert!(!scheduler.handle_error(OprishRoute::CreateMessage, &error, 1_000));
+        assert_eq!(scheduler.schedule(OprishRoute::CreateMessage, 1_000), 0);
+    }
+
+    #[test]
+    fn huge_uploads_do_not_overflow() {
+        let mut scheduler = scheduler();
+        let reset_after = scheduler
+            .rate_limits()
+            .effis
+            .attachments
+            .reset_after
+            .as_millis() as u64;
+
+        assert_eq!(
+            scheduler.schedule_upload(EffisRoute::Attachments, u64::MAX, 1_000),
+            0
+        );
+        assert_eq!(
+            scheduler.schedule_upload(EffisRoute::Attachments, u64::MAX, 1_000),
+            reset_after
+        );
+        assert_eq!(
+            scheduler.schedule_upload(EffisRoute::Attachments, 1, 1_000),
+            2 * reset_after
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    sync::{Mutex, PoisonError},
+};
+
+/// A request to account for in a rate limit window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowHit<'a> {
+    /// The key of the window, unique for every bucket and identifier pair.
+    pub key: &'a str,
+    /// The current time in milliseconds since the UNIX epoch.
+    pub now: u64,
+    /// The length of the window in milliseconds.
+    pub reset_after: u64,
+    /// The amount of requests that can be made within the window.
+    pub limit: u32,
+    /// The size of the request in bytes and the byte budget of the window, if it has one.
+    pub bytes: Option<(u64, u64)>,
+}
+
+/// The state of a rate limit window after a [`WindowHit`] was accounted for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowState {
+    /// Whether the request is allowed, in which case it was counted towards the window.
+    pub allowed: bool,
+    /// The amount of requests made within the window.
+    pub count: u32,
+    /// The amount of bytes sent within the window.
+    pub bytes: u64,
+    /// When the window resets in milliseconds since the UNIX epoch.
+    pub reset_at: u64,
+}
+
+/// Storage for the windows of a [`RateLimiter`](super::RateLimiter).
+///
+/// Implementations have to check and update a window atomically so that a single store can be
+/// shared by multiple limiters, possibly in different processes.
+pub trait RateLimitStore: Send + Sync {
+    /// The error returned when the store fails.
+    type Error: Send;
+
+    /// Atomically account for a request in its window, resetting the window first if it's
+    /// expired.
+    ///
+    /// A request is allowed if it neither goes over the window's request limit nor its byte
+    /// budget, rejected requests are not counted.
+    fn hit(
+        &self,
+        hit: WindowHit<'_>,
+    ) -> impl Future<Output = Result<WindowState, Self::Error>> + Send;
+
+    /// Forget a window.
+    fn reset(&self, key: &str) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// A [`RateLimitStore`] that keeps windows in the process' memory.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    windows: Mutex<HashMap<String, WindowState>>,
+}
+
+impl MemoryStore {
+    /// Create a new empty [`MemoryStore`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget every window that has already reset by `now`, freeing up their memory.
+    pub fn clear_expired(&self, now: u64) {
+        self.windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|_, window| window.reset_at > now);
+    }
+}
+
+impl RateLimitStore for MemoryStore {
+    type Error = Infallible;
+
+    async fn hit(&self, hit: WindowHit<'_>) -> Result<WindowState, Self::Error> {
+        let mut windows = self.windows.lock().unwrap_or_else(PoisonError::into_inner);
+        let window = windows.entry(hit.key.to_string()).or_insert(WindowState {
+            allowed: false,
+            count: 0,
+            bytes: 0,
+            reset_at: 0,
+        });
+        if window.reset_at <= hit.now {
+            window.reset_at = hit.now + hit.reset_after;
+            window.count = 0;
+            window.bytes = 0;
+        }
+
+        window.allowed = window.count < hit.limit
+            && hit
+                .bytes
+                .is_none_or(|(size, byte_limit)| window.bytes.saturating_add(size) <= byte_limit);
+        if window.allowed {
+            window.count += 1;
+            window.bytes = window
+                .bytes
+                .saturating_add(hit.bytes.map_or(0, |(size, _)| size));
+        }
+        Ok(window.clone())
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), Self::Error> {
+        self.windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::fmt::Debug;
+
+    use super::*;
+
+    const NOW: u64 = 1_000;
+    const RESET_AFTER: u64 = 10_000;
+
+    fn hit(key: &str, now: u64, limit: u32, bytes: Option<(u64, u64)>) -> WindowHit<'_> {
+        WindowHit {
+            key,
+            now,
+            reset_after: RESET_AFTER,
+            limit,
+            bytes,
+        }
+    }
+
+    fn state(allowed: bool, count: u32, bytes: u64, reset_at: u64) -> WindowState {
+        WindowState {
+            allowed,
+            count,
+            bytes,
+            reset_at,
+        }
+    }
+
+    /// Check that a [`RateLimitStore`] implements the windows it's expected to.
+    ///
+    /// Every key the suite uses starts with `conformance:` and is reset before it's used.
+    pub(in crate::rate_limit) async fn conformance<S>(store: &S)
+    where
+        S: RateLimitStore,
+        S::Error: Debug,
+    {
+        for key in [
+            "conformance:limit",
+            "conformance:other",
+            "conformance:window",
+            "conformance:bytes",
+            "conformance:reset",
+        ] {
+            store.reset(key).await.unwrap();
+        }
+
+        limit(store).await;
+        window_reset(store).await;
+        byte_budget(store).await;
+        reset(store).await;
+    }
+
+    async fn limit<S: RateLimitStore>(store: &S)
+    where
+        S::Error: Debug,
+    {
+        let key = "conformance:limit";
+        let reset_at = NOW + RESET_AFTER;
+        for count in 1..=3 {
+            let window = store.hit(hit(key, NOW, 3, None)).await;
+            assert_eq!(window.unwrap(), state(true, count, 0, reset_at));
+        }
+        // Rejected requests aren't counted.
+        for _ in 0..2 {
+            let window = store.hit(hit(key, NOW + 5, 3, None)).await;
+            assert_eq!(window.unwrap(), state(false, 3, 0, reset_at));
+        }
+        // Other keys have their own windows.
+        let window = store.hit(hit("conformance:other", NOW, 3, None)).await;
+        assert_eq!(window.unwrap(), state(true, 1, 0, reset_at));
+    }
+
+    async fn window_reset<S: RateLimitStore>(store: &S)
+    where
+        S::Error: Debug,
+    {
+        let key = "conformance:window";
+        let reset_at = NOW + RESET_AFTER;
+        let window = store.hit(hit(key, NOW, 1, None)).await;
+        assert_eq!(window.unwrap(), state(true, 1, 0, reset_at));
+        let window = store.hit(hit(key, reset_at - 1, 1, None)).await;
+        assert_eq!(window.unwrap(), state(false, 1, 0, reset_at));
+
+        // The window resets right at `reset_at` and the new one starts with the request.
+        let window = store.hit(hit(key, reset_at, 1, None)).await;
+        assert_eq!(window.unwrap(), state(true, 1, 0, reset_at + RESET_AFTER));
+    }
+
+    async fn byte_budget<S: RateLimitStore>(store: &S)
+    where
+        S::Error: Debug,
+    {
+        let key = "conformance:bytes";
+        let reset_at = NOW + RESET_AFTER;
+        let window = store.hit(hit(key, NOW, 10, Some((60, 100)))).await;
+        assert_eq!(window.unwrap(), state(true, 1, 60, reset_at));
+
+        // Going over the budget is rejected even with requests left.
+        let window = store.hit(hit(key, NOW, 10, Some((41, 100)))).await;
+        assert_eq!(window.unwrap(), state(false, 1, 60, reset_at));
+
+        // Filling it up exactly isn't.
+        let window = store.hit(hit(key, NOW, 10, Some((40, 100)))).await;
+        assert_eq!(window.unwrap(), state(true, 2, 100, reset_at));
+        let window = store.hit(hit(key, NOW, 10, Some((1, 100)))).await;
+        assert_eq!(window.unwrap(), state(false, 2, 100, reset_at));
+
+        // Requests without a size still count towards the request limit.
+        let window = store.hit(hit(key, NOW, 10, Some((0, 100)))).await;
+        assert_eq!(window.unwrap(), state(true, 3, 100, reset_at));
+
+        let window = store.hit(hit(key, reset_at, 10, Some((100, 100)))).await;
+        assert_eq!(window.unwrap(), state(true, 1, 100, reset_at + RESET_AFTER));
+    }
+
+    async fn reset<S: RateLimitStore>(store: &S)
+    where
+        S::Error: Debug,
+    {
+        let key = "conformance:reset";
+        let reset_at = NOW + RESET_AFTER;
+        let window = store.hit(hit(key, NOW, 1, None)).await;
+        assert_eq!(window.unwrap(), state(true, 1, 0, reset_at));
+        let window = store.hit(hit(key, NOW + 1, 1, None)).await;
+        assert_eq!(window.unwrap(), state(false, 1, 0, reset_at));
+
+        store.reset(key).await.unwrap();
+        let window = store.hit(hit(key, NOW + 1, 1, None)).await;
+        assert_eq!(window.unwrap(), state(true, 1, 0, NOW + 1 + RESET_AFTER));
+
+        // Resetting a window that doesn't exist is fine.
+        store.reset("conformance:missing").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn memory_store() {
+        conformance(&MemoryStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn memory_store_clear_expired() {
+        let store = MemoryStore::new();
+        store.hit(hit("a", NOW, 1, None)).await.unwrap();
+        store.hit(hit("b", NOW + 5, 1, None)).await.unwrap();
+
+        store.clear_expired(NOW + RESET_AFTER);
+        let windows = store.windows.lock().unwrap();
+        assert!(!windows.contains_key("a"));
+        assert!(windows.contains_key("b"));
+    }
+}
@@ -2,6 +2,8 @@ use std::net::IpAddr;
 
 use serde::{Deserialize, Serialize};
 
+use crate::Id;
+
 /// The session payload.
 ///
 /// The user should ideally have one session for every client they have on every device.
@@ -21,9 +23,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Session {
     /// The session's ID.
-    pub id: u64,
+    pub id: Id,
     /// The session user's ID.
-    pub user_id: u64,
+    pub user_id: Id,
     /// The session's platform (linux, windows, mac, etc.)
     pub platform: String,
     /// The client the session was created by.
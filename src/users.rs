@@ -3,6 +3,8 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 use serde_with::rust::double_option;
 
+use crate::Id;
+
 /// The type of a user's status.
 ///
 /// This is a string.
@@ -63,7 +65,7 @@ pub struct Status {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct User {
     /// The user's ID.
-    pub id: u64,
+    pub id: Id,
     /// The user's username. This field has to be between 2 and 32 characters long.
     pub username: String,
     /// The user's display name. This field has to be between 2 and 32 characters long.
@@ -78,10 +80,10 @@ pub struct User {
     pub bio: Option<String>,
     /// The user's avatar. This field has to be a valid file ID in the "avatar" bucket.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub avatar: Option<u64>,
+    pub avatar: Option<Id>,
     /// The user's banner. This field has to be a valid file ID in the "banner" bucket.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub banner: Option<u64>,
+    pub banner: Option<Id>,
     /// The user's badges as a bitfield.
     pub badges: u64,
     /// The user's instance-wide permissions as a bitfield.
@@ -131,6 +133,11 @@ pub struct UserCreate {
     pub email: String,
     /// The user's password.
     pub password: String,
+    /// The invite code the user was given.
+    ///
+    /// This is only required on instances where registration is invite-only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invite_code: Option<String>,
 }
 
 /// The UpdateUser payload. Any field set to `null`, `undefined` or is missing will be disregarded
@@ -208,14 +215,14 @@ pub struct UpdateUserProfile {
         skip_serializing_if = "Option::is_none",
         with = "double_option"
     )]
-    pub avatar: Option<Option<u64>>,
+    pub avatar: Option<Option<Id>>,
     /// The user's new banner. This field has to be a valid file ID in the "banner" bucket.
     #[serde(
         default,
         skip_serializing_if = "Option::is_none",
         with = "double_option"
     )]
-    pub banner: Option<Option<u64>>,
+    pub banner: Option<Option<Id>>,
 }
 
 /// The CreatePasswordResetCode payload. This is used when a user wants to generate a code